@@ -0,0 +1,581 @@
+use core::fmt;
+
+use ephemeris::{
+    analytic_shadow_angle_from_unix_timestamp, shadow_angle_from_unix_timestamp, Centidegrees,
+    Ephemeris, EphemerisError,
+};
+
+use crate::hal::{Backlight, GlobeMotor, IndexSensor, Storage, WallClock};
+use crate::homing::{Homing, HomingAction, HomingConfig, HomingError};
+use crate::position::{Move, PositionConfig, Positioner};
+
+/// Storage key of the last globe position, motor steps from the index as
+/// little endian i64 followed by the last move direction as i8
+const POSITION_KEY: &str = "position";
+/// Storage key of the marker telling the stored position can be trusted,
+/// removed while the globe moves
+const CLEAN_KEY: &str = "clean";
+const CLEAN_MARKER: [u8; 4] = *b"moon";
+
+/// Reasons the moon application can fail
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// a device reported an error
+    Hal(E),
+    /// globe could not be brought to its mechanical reference
+    Homing(HomingError),
+    /// bundled ephemeris cannot be used
+    Ephemeris(EphemerisError),
+}
+
+impl<E: fmt::Debug> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Hal(e) => write!(f, "device error: {e:?}"),
+            Error::Homing(e) => write!(f, "{e}"),
+            Error::Ephemeris(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// Outcome of an application tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// wall clock is not synchronized yet, globe was left untouched
+    Unsynced,
+    /// globe shows the shadow angle found in the ephemeris
+    Tracking(Centidegrees),
+    /// ephemeris expired, globe shows the analytic model shadow angle
+    Expired(Centidegrees),
+}
+
+/// Hardware the application runs on
+pub struct Devices<M, I, B, C, S> {
+    pub motor: M,
+    pub index: I,
+    pub backlight: B,
+    pub clock: C,
+    pub storage: S,
+}
+
+/// Moon globe application, platform neutral
+///
+/// Tracking moves of a globe whose position is confirmed are started with
+/// `GlobeMotor::start` so `tick` does not wait for them, their position is
+/// saved on a later tick once the globe is at rest. Homing and moves watching
+/// for the index read the sensor between steps and block until done.
+pub struct Moon<'a, D: ?Sized, M, I, B, C, S> {
+    data: &'a D,
+    devices: Devices<M, I, B, C, S>,
+    positioner: Positioner,
+    homing: HomingConfig,
+    homed: bool,
+    // position was checked against the index since it was homed or restored
+    confirmed: bool,
+    // a move started in the background is yet to be saved
+    unsaved_move: bool,
+}
+
+impl<'a, D, M, I, B, C, S, E> Moon<'a, D, M, I, B, C, S>
+where
+    D: Ephemeris + ?Sized,
+    M: GlobeMotor<Error = E>,
+    I: IndexSensor<Error = E>,
+    B: Backlight<Error = E>,
+    C: WallClock,
+    S: Storage<Error = E>,
+{
+    pub fn new(data: &'a D, devices: Devices<M, I, B, C, S>) -> Self {
+        Moon {
+            data,
+            devices,
+            positioner: Positioner::new(PositionConfig::DEFAULT),
+            homing: HomingConfig::DEFAULT,
+            homed: false,
+            confirmed: false,
+            unsaved_move: false,
+        }
+    }
+
+    /// Use provided globe drive setup instead of the default one
+    ///
+    /// Homing search limits are fitted to the new globe revolution, call
+    /// `homing` afterwards to set them by hand.
+    pub fn positioning(mut self, config: PositionConfig) -> Self {
+        self.positioner = Positioner::new(config);
+        self.homing = self.homing.for_globe_rev(config.globe_rev_steps());
+        self.homed = false;
+        self
+    }
+
+    /// Use provided homing tuning instead of the default one, step limits
+    /// are taken as is
+    pub fn homing(mut self, config: HomingConfig) -> Self {
+        self.homing = config;
+        self
+    }
+
+    pub fn devices(&self) -> &Devices<M, I, B, C, S> {
+        &self.devices
+    }
+
+    pub fn devices_mut(&mut self) -> &mut Devices<M, I, B, C, S> {
+        &mut self.devices
+    }
+
+    pub fn into_devices(self) -> Devices<M, I, B, C, S> {
+        self.devices
+    }
+
+    /// Motor position in steps from the mechanical reference, None until
+    /// homed
+    pub fn position(&self) -> Option<i64> {
+        self.homed.then(|| self.positioner.position())
+    }
+
+    /// True once globe position is known to match the index, false while a
+    /// position restored from storage awaits the index to pass by
+    pub fn is_confirmed(&self) -> bool {
+        self.homed && self.confirmed
+    }
+
+    /// Save globe position and mark it as trustworthy
+    fn persist(&mut self) -> Result<(), Error<E>> {
+        let mut record = [0; 9];
+        record[..8].copy_from_slice(&self.positioner.position().to_le_bytes());
+        record[8] = self.positioner.last_direction() as i8 as u8;
+
+        let storage = &mut self.devices.storage;
+        storage.set(POSITION_KEY, &record).map_err(Error::Hal)?;
+        storage.set(CLEAN_KEY, &CLEAN_MARKER).map_err(Error::Hal)
+    }
+
+    /// Restore globe position saved before the last shutdown, return false
+    /// if it cannot be trusted
+    ///
+    /// Position was saved once the globe stood still, it is confirmed the
+    /// next time the index passes by instead of homing straight away.
+    pub fn resume(&mut self) -> Result<bool, Error<E>> {
+        let storage = &mut self.devices.storage;
+        let mut marker = [0; 4];
+        if storage.get(CLEAN_KEY, &mut marker).map_err(Error::Hal)? != Some(4)
+            || marker != CLEAN_MARKER
+        {
+            return Ok(false);
+        }
+        let mut record = [0; 9];
+        if storage.get(POSITION_KEY, &mut record).map_err(Error::Hal)? != Some(9) {
+            return Ok(false);
+        }
+
+        let mut position = [0; 8];
+        position.copy_from_slice(&record[..8]);
+        self.positioner
+            .restore(i64::from_le_bytes(position), record[8] as i8 as i64);
+        self.homed = true;
+        self.confirmed = false;
+        Ok(true)
+    }
+
+    /// Bring globe to its mechanical reference, see `Homing`
+    pub fn home(&mut self) -> Result<(), Error<E>> {
+        self.homed = false;
+        self.devices.storage.remove(CLEAN_KEY).map_err(Error::Hal)?;
+        let mut homing = Homing::new(self.homing);
+        loop {
+            let detected = self.devices.index.is_detected().map_err(Error::Hal)?;
+            match homing.next(detected) {
+                HomingAction::Step(steps) => self.devices.motor.step(steps).map_err(Error::Hal)?,
+                HomingAction::Done => break,
+                HomingAction::Failed(e) => {
+                    self.devices.motor.release().map_err(Error::Hal)?;
+                    return Err(Error::Homing(e));
+                }
+            }
+        }
+        self.devices.motor.release().map_err(Error::Hal)?;
+        self.positioner.reset();
+        self.homed = true;
+        self.confirmed = true;
+        self.unsaved_move = false;
+        self.persist()
+    }
+
+    /// Take planned move step by step watching for the index trailing edge,
+    /// correct position once it is seen
+    fn step_watching_index(&mut self, planned: Move) -> Result<(), Error<E>> {
+        let motor = &mut self.devices.motor;
+        let index = &mut self.devices.index;
+
+        // gear slack does not turn the globe
+        if planned.slack != 0 {
+            motor.step(planned.slack as i32).map_err(Error::Hal)?;
+        }
+
+        let direction = planned.delta.signum();
+        let mut position = self.positioner.position() - planned.delta;
+        let mut detected = index.is_detected().map_err(Error::Hal)?;
+        for _ in 0..planned.delta.abs() {
+            motor.step(direction as i32).map_err(Error::Hal)?;
+            position += direction;
+            let now = index.is_detected().map_err(Error::Hal)?;
+
+            // trailing edge is the first position clear of the flag when
+            // turning forward
+            let edge = match (detected, now, direction) {
+                (true, false, 1) => Some(position),
+                (false, true, -1) => Some(position + 1),
+                _ => None,
+            };
+            if let Some(edge) = edge {
+                // remaining steps are taken as planned
+                let remaining = self.positioner.position() - position;
+                self.positioner.index_seen(edge);
+                self.confirmed = true;
+                return motor.step(remaining as i32).map_err(Error::Hal);
+            }
+            detected = now;
+        }
+        Ok(())
+    }
+
+    /// Turn globe to provided shadow angle, restoring its position or
+    /// homing it first if needed
+    fn move_to(&mut self, angle: Centidegrees) -> Result<(), Error<E>> {
+        // globe is left to a move started on a previous tick, saved once done
+        if self.unsaved_move {
+            if self.devices.motor.is_moving().map_err(Error::Hal)? {
+                return Ok(());
+            }
+            self.unsaved_move = false;
+            self.persist()?;
+        }
+        if !self.homed && !self.resume()? {
+            self.home()?;
+        }
+
+        let planned = self.positioner.move_to(angle);
+        if planned.steps() == 0 {
+            return Ok(());
+        }
+
+        // position cannot be trusted until the globe stops
+        self.devices.storage.remove(CLEAN_KEY).map_err(Error::Hal)?;
+        if self.confirmed {
+            let steps = planned.steps() as i32;
+            self.devices.motor.start(steps).map_err(Error::Hal)?;
+        } else {
+            self.step_watching_index(planned)?;
+            if self.confirmed {
+                // bring globe back on target once position got corrected
+                let steps = self.positioner.move_to(angle).steps() as i32;
+                self.devices.motor.step(steps).map_err(Error::Hal)?;
+            }
+        }
+        // do not keep coils energized between moves
+        self.devices.motor.release().map_err(Error::Hal)?;
+        if self.devices.motor.is_moving().map_err(Error::Hal)? {
+            self.unsaved_move = true;
+            return Ok(());
+        }
+        self.persist()
+    }
+
+    /// Run one iteration of the application, turning the globe to the
+    /// current shadow angle
+    pub fn tick(&mut self) -> Result<Status, Error<E>> {
+        let Some(now) = self.devices.clock.now() else {
+            self.devices
+                .backlight
+                .set_color(0, 0, 0)
+                .map_err(Error::Hal)?;
+            return Ok(Status::Unsynced);
+        };
+
+        let (status, angle) = match shadow_angle_from_unix_timestamp(self.data, now) {
+            Ok(angle) => (Status::Tracking(angle), angle),
+            Err(EphemerisError::AfterEnd { .. }) => {
+                let angle = analytic_shadow_angle_from_unix_timestamp(now);
+                (Status::Expired(angle), angle)
+            }
+            Err(e) => return Err(Error::Ephemeris(e)),
+        };
+        self.move_to(angle)?;
+        self.devices
+            .backlight
+            .set_color(255, 255, 255)
+            .map_err(Error::Hal)?;
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::homing::HomingStage;
+    use crate::mock::{self, MockClock};
+    use crate::position::{Direction, STEPS_PER_REV};
+    use ephemeris::{EphemerisSet, MoonEphemeris, MOON_EPHEMERIS};
+
+    const INDEX_AT: i64 = 1000;
+    const INDEX_WIDTH: i64 = 40;
+
+    fn mock_moon<D: Ephemeris + ?Sized>(data: &D, clock: MockClock) -> mock::MockMoon<'_, D> {
+        Moon::new(
+            data,
+            mock::devices(STEPS_PER_REV, INDEX_AT, INDEX_WIDTH, clock),
+        )
+    }
+
+    #[test]
+    fn homing_stops_past_index() {
+        let mut moon = mock_moon(&MOON_EPHEMERIS, MockClock::unsynced());
+        moon.home().unwrap();
+        assert_eq!(moon.position(), Some(0));
+        // trailing edge of the index flag
+        assert_eq!(moon.devices().motor.position(), INDEX_AT + INDEX_WIDTH);
+
+        // a missing flag is reported
+        moon.devices_mut().index.unplug();
+        assert_eq!(
+            moon.home(),
+            Err(Error::Homing(HomingError::StuckLow {
+                stage: HomingStage::FindIndex
+            }))
+        );
+        assert_eq!(moon.position(), None);
+    }
+
+    #[test]
+    fn unsynced_clock_leaves_globe_alone() {
+        let mut moon = mock_moon(&MOON_EPHEMERIS, MockClock::unsynced());
+        assert_eq!(moon.tick(), Ok(Status::Unsynced));
+        assert_eq!(moon.position(), None);
+        assert_eq!(moon.devices().backlight.color(), (0, 0, 0));
+    }
+
+    #[test]
+    fn chained_tables_are_tracked() {
+        // bundled table handed over to a fresher copy of itself
+        let segments = [
+            MoonEphemeris {
+                shadow: &MOON_EPHEMERIS.shadow[..48],
+                elevation: &MOON_EPHEMERIS.elevation[..48],
+                ..MOON_EPHEMERIS
+            },
+            MoonEphemeris {
+                start: MOON_EPHEMERIS.start + 24 * 3600,
+                shadow: &MOON_EPHEMERIS.shadow[24..],
+                elevation: &MOON_EPHEMERIS.elevation[24..],
+                ..MOON_EPHEMERIS
+            },
+        ];
+        let set = EphemerisSet::new(&segments);
+
+        let at = MOON_EPHEMERIS.start as i64 + 30 * 3600 + 600;
+        let mut chained = mock_moon(&set, MockClock::at(at));
+        let mut single = mock_moon(&MOON_EPHEMERIS, MockClock::at(at));
+        assert_eq!(chained.tick(), single.tick());
+        assert!(matches!(chained.tick(), Ok(Status::Tracking(_))));
+    }
+
+    #[test]
+    fn simulated_lunar_month() {
+        let start = MOON_EPHEMERIS.start as i64 + 86400;
+        let clock = MockClock::at(start);
+        let mut moon = mock_moon(&MOON_EPHEMERIS, clock.clone());
+
+        let zero = INDEX_AT + INDEX_WIDTH;
+        let mut travel = 0;
+        let mut last = None;
+        for _ in 0..30 * 24 {
+            let Ok(Status::Tracking(angle)) = moon.tick() else {
+                panic!("globe stopped tracking");
+            };
+
+            // globe matches the table within a step
+            let position = moon.devices().motor.position();
+            let target = (angle.0 as i64 * STEPS_PER_REV as i64 + 18000) / 36000;
+            assert_eq!(
+                (position - zero - target).rem_euclid(STEPS_PER_REV as i64),
+                0
+            );
+
+            if let Some(last) = last {
+                travel += position - last;
+            }
+            last = Some(position);
+            assert!(moon.devices().backlight.color() != (0, 0, 0));
+            clock.advance(3600);
+        }
+
+        // globe turned backwards about once during the month
+        let turns = travel as f64 / STEPS_PER_REV as f64;
+        assert!(turns < -0.95 && turns > -1.05, "{turns}");
+        // coils are released after each move
+        assert!(!moon.devices().motor.is_energized());
+    }
+
+    #[test]
+    fn positioning_setup_is_followed() {
+        let clock = MockClock::at(MOON_EPHEMERIS.start as i64);
+        // one index per globe revolution, twice the motor one
+        let devices = mock::devices(2 * STEPS_PER_REV, INDEX_AT, INDEX_WIDTH, clock.clone());
+        let mut moon = Moon::new(&MOON_EPHEMERIS, devices).positioning(PositionConfig {
+            gear_ratio: (2, 1),
+            zero_index_offset: 50,
+            direction: Direction::Backward,
+            ..Default::default()
+        });
+
+        let zero = INDEX_AT + INDEX_WIDTH;
+        let mut last = zero;
+        for _ in 0..48 {
+            assert!(matches!(moon.tick(), Ok(Status::Tracking(_))));
+            let position = moon.devices().motor.position();
+            assert_eq!(Some(position - zero), moon.position());
+            assert!(position <= last);
+            last = position;
+            clock.advance(3600);
+        }
+    }
+
+    #[test]
+    fn geared_globe_homes() {
+        // index lies further than a motor revolution away
+        let clock = MockClock::at(MOON_EPHEMERIS.start as i64);
+        let devices = mock::devices(2 * STEPS_PER_REV, 7000, INDEX_WIDTH, clock);
+        let mut moon = Moon::new(&MOON_EPHEMERIS, devices).positioning(PositionConfig {
+            gear_ratio: (2, 1),
+            ..Default::default()
+        });
+
+        assert!(matches!(moon.tick(), Ok(Status::Tracking(_))));
+        assert!(moon.is_confirmed());
+
+        // a missing flag is still reported past a globe revolution
+        moon.devices_mut().index.unplug();
+        let before = moon.devices().motor.position();
+        assert!(matches!(moon.home(), Err(Error::Homing(_))));
+        let swept = (moon.devices().motor.position() - before).abs();
+        assert!(swept <= 2 * STEPS_PER_REV as i64 + STEPS_PER_REV as i64 / 2);
+    }
+
+    /// Run hourly ticks for provided number of hours
+    fn run_hours<D: Ephemeris + ?Sized>(
+        moon: &mut mock::MockMoon<'_, D>,
+        clock: &MockClock,
+        hours: i64,
+    ) {
+        for _ in 0..hours {
+            assert!(matches!(moon.tick(), Ok(Status::Tracking(_))));
+            clock.advance(3600);
+        }
+    }
+
+    #[test]
+    fn tracking_moves_run_in_background() {
+        let clock = MockClock::at(MOON_EPHEMERIS.start as i64);
+        let mut moon = mock_moon(&MOON_EPHEMERIS, clock.clone());
+        run_hours(&mut moon, &clock, 1);
+        assert!(moon.is_confirmed());
+        moon.devices_mut().motor.run_in_background();
+        let clean = |moon: &mut mock::MockMoon<'_, MoonEphemeris>| {
+            let storage = &mut moon.devices_mut().storage;
+            storage.get(CLEAN_KEY, &mut [0; 4]).unwrap().is_some()
+        };
+
+        // tick returns while the globe is on its way, unsaved
+        clock.advance(6 * 3600);
+        let before = moon.devices().motor.position();
+        assert!(matches!(moon.tick(), Ok(Status::Tracking(_))));
+        assert_eq!(moon.devices().motor.position(), before);
+        assert!(moon.devices().motor.is_energized());
+        assert!(!clean(&mut moon));
+
+        // ticks leave a moving globe alone
+        assert!(matches!(moon.tick(), Ok(Status::Tracking(_))));
+        assert!(!clean(&mut moon));
+
+        // coils are let go once at rest, position is saved on the next tick
+        moon.devices_mut().motor.finish();
+        assert!(!moon.devices().motor.is_energized());
+        assert_ne!(moon.devices().motor.position(), before);
+        assert!(matches!(moon.tick(), Ok(Status::Tracking(_))));
+        assert!(clean(&mut moon));
+        let zero = INDEX_AT + INDEX_WIDTH;
+        assert_eq!(
+            Some(moon.devices().motor.position() - zero),
+            moon.position()
+        );
+    }
+
+    #[test]
+    fn clean_reboot_skips_homing() {
+        let clock = MockClock::at(MOON_EPHEMERIS.start as i64);
+        let mut moon = mock_moon(&MOON_EPHEMERIS, clock.clone());
+        run_hours(&mut moon, &clock, 24);
+        assert!(moon.is_confirmed());
+
+        // power cycle, globe is turned by hand meanwhile
+        let mut devices = moon.into_devices();
+        devices.motor.step(30).unwrap();
+        let before = devices.motor.position();
+
+        let mut moon = Moon::new(&MOON_EPHEMERIS, devices);
+        run_hours(&mut moon, &clock, 1);
+        // no homing sweep, stored position is trusted
+        assert!((moon.devices().motor.position() - before).abs() < 20);
+        assert!(!moon.is_confirmed());
+
+        // index passes by within a lunation and position gets corrected
+        let zero = INDEX_AT + INDEX_WIDTH;
+        run_hours(&mut moon, &clock, 30 * 24);
+        assert!(moon.is_confirmed());
+        assert_eq!(
+            Some(moon.devices().motor.position() - zero),
+            moon.position()
+        );
+    }
+
+    #[test]
+    fn interrupted_move_homes_on_reboot() {
+        let clock = MockClock::at(MOON_EPHEMERIS.start as i64);
+        let mut moon = mock_moon(&MOON_EPHEMERIS, clock.clone());
+        run_hours(&mut moon, &clock, 24);
+
+        // power was lost while the globe was moving
+        let mut devices = moon.into_devices();
+        devices.storage.remove(CLEAN_KEY).unwrap();
+        devices.motor.step(-300).unwrap();
+
+        let mut moon = Moon::new(&MOON_EPHEMERIS, devices);
+        run_hours(&mut moon, &clock, 1);
+        assert!(moon.is_confirmed());
+        let zero = INDEX_AT + INDEX_WIDTH;
+        let position = moon.devices().motor.position() - zero;
+        assert_eq!(
+            position.rem_euclid(STEPS_PER_REV as i64),
+            moon.position().unwrap().rem_euclid(STEPS_PER_REV as i64)
+        );
+    }
+
+    #[test]
+    fn expired_table_falls_back_to_model() {
+        const EPHEMERIS: MoonEphemeris = MoonEphemeris {
+            start: 1000,
+            period: 3600,
+            shadow: &[100, 90],
+            elevation: &[0, 0],
+        };
+        let mut moon = mock_moon(&EPHEMERIS, MockClock::at(1_762_879_028));
+        let expected = analytic_shadow_angle_from_unix_timestamp(1_762_879_028);
+        assert_eq!(moon.tick(), Ok(Status::Expired(expected)));
+
+        let mut moon = mock_moon(&EPHEMERIS, MockClock::at(0));
+        assert_eq!(
+            moon.tick(),
+            Err(Error::Ephemeris(EphemerisError::BeforeStart))
+        );
+    }
+}
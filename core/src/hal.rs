@@ -0,0 +1,63 @@
+/// Stepper motor turning the moon globe
+pub trait GlobeMotor {
+    type Error;
+
+    /// Turn globe by provided number of motor steps, blocking until done
+    ///
+    /// Positive steps turn the globe towards increasing angles.
+    fn step(&mut self, steps: i32) -> Result<(), Self::Error>;
+
+    /// Start turning globe by provided number of motor steps and return
+    /// without waiting, steps add up to those of a move under way
+    ///
+    /// Motors that cannot move in the background block as `step` does.
+    fn start(&mut self, steps: i32) -> Result<(), Self::Error> {
+        self.step(steps)
+    }
+
+    /// True while a move started with `start` is under way
+    fn is_moving(&mut self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    /// Stop driving the coils once the globe is at rest, globe may then be
+    /// turned by hand
+    fn release(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Optical fork detecting the globe mechanical reference
+pub trait IndexSensor {
+    type Error;
+
+    /// True while the index flag sits inside the fork
+    fn is_detected(&mut self) -> Result<bool, Self::Error>;
+}
+
+/// RGB light behind the globe
+pub trait Backlight {
+    type Error;
+
+    /// Set light color, each component ranging [0;255]
+    fn set_color(&mut self, r: u8, g: u8, b: u8) -> Result<(), Self::Error>;
+}
+
+/// Source of the current date
+pub trait WallClock {
+    /// Current unix timestamp, None until the clock got synchronized
+    fn now(&self) -> Option<i64>;
+}
+
+/// Small persistent key value store
+pub trait Storage {
+    type Error;
+
+    /// Read value stored under provided key into `buf`, return its length
+    /// or None if there is no such key
+    fn get(&mut self, key: &str, buf: &mut [u8]) -> Result<Option<usize>, Self::Error>;
+
+    /// Store value under provided key, replacing any previous one
+    fn set(&mut self, key: &str, value: &[u8]) -> Result<(), Self::Error>;
+
+    /// Remove value stored under provided key, if any
+    fn remove(&mut self, key: &str) -> Result<(), Self::Error>;
+}
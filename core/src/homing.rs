@@ -0,0 +1,298 @@
+use core::fmt;
+
+use crate::position::STEPS_PER_REV;
+
+/// Steps of a homing procedure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HomingStage {
+    /// turning fast until the index leaves the sensor
+    LeaveIndex,
+    /// turning fast until the index reaches the sensor
+    FindIndex,
+    /// turning forward slowly until the index trailing edge
+    FindEdge,
+}
+
+/// Reasons homing can fail, each carries the stage the sensor got stuck in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HomingError {
+    /// sensor kept seeing the index, fork LED off or flag jammed in the fork
+    StuckHigh { stage: HomingStage },
+    /// sensor never saw the index, flag missing or sensor disconnected
+    StuckLow { stage: HomingStage },
+}
+
+impl fmt::Display for HomingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HomingError::StuckHigh { stage } => {
+                write!(f, "index sensor stuck high while homing ({stage:?})")
+            }
+            HomingError::StuckLow { stage } => {
+                write!(f, "index sensor stuck low while homing ({stage:?})")
+            }
+        }
+    }
+}
+
+/// Homing procedure tuning, step counts are motor steps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HomingConfig {
+    // steps taken at once while looking for the index
+    pub fast_steps: i32,
+    // steps taken at once while locating the index edge
+    pub slow_steps: i32,
+    // steps each stage may take before the sensor is deemed stuck
+    pub leave_max_steps: i32,
+    pub find_max_steps: i32,
+    pub edge_max_steps: i32,
+    // attempts made before giving up, searching in alternate directions
+    pub attempts: u8,
+}
+
+impl HomingConfig {
+    /// Globe directly on the motor shaft, stuck sensors are detected after
+    /// a bit more than a revolution
+    pub const DEFAULT: HomingConfig = HomingConfig {
+        fast_steps: 10,
+        slow_steps: 1,
+        leave_max_steps: STEPS_PER_REV + STEPS_PER_REV / 4,
+        find_max_steps: STEPS_PER_REV + STEPS_PER_REV / 4,
+        edge_max_steps: 64,
+        attempts: 3,
+    };
+
+    /// Same tuning with index search limits fitted to a globe revolution of
+    /// provided number of motor steps
+    pub const fn for_globe_rev(self, steps: i32) -> Self {
+        HomingConfig {
+            leave_max_steps: steps + steps / 4,
+            find_max_steps: steps + steps / 4,
+            ..self
+        }
+    }
+}
+
+impl Default for HomingConfig {
+    fn default() -> Self {
+        HomingConfig::DEFAULT
+    }
+}
+
+/// What to do next while homing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HomingAction {
+    /// take provided signed number of steps then read the sensor again
+    Step(i32),
+    /// globe sits on the index trailing edge, reached turning forward
+    Done,
+    /// every attempt failed, holds the last error
+    Failed(HomingError),
+}
+
+/// Homing state machine, fed with index sensor readings
+///
+/// Each attempt turns until the index leaves the sensor, then up to the next
+/// index and finally forward slowly past its trailing edge, so the globe
+/// always ends on the same edge with the gear slack taken up forward.
+/// Failed attempts are retried searching in the other direction, in case
+/// the globe is blocked one way.
+#[derive(Debug, Clone)]
+pub struct Homing {
+    config: HomingConfig,
+    stage: HomingStage,
+    // search direction of the current attempt
+    direction: i32,
+    // attempts failed so far
+    failures: u8,
+    // steps taken in the current stage
+    steps: i32,
+    outcome: Option<HomingAction>,
+}
+
+impl Homing {
+    pub const fn new(config: HomingConfig) -> Self {
+        Homing {
+            config,
+            stage: HomingStage::LeaveIndex,
+            direction: 1,
+            failures: 0,
+            steps: 0,
+            outcome: None,
+        }
+    }
+
+    pub fn stage(&self) -> HomingStage {
+        self.stage
+    }
+
+    /// Number of failed attempts so far
+    pub fn failures(&self) -> u8 {
+        self.failures
+    }
+
+    /// Advance with provided index sensor reading
+    pub fn next(&mut self, detected: bool) -> HomingAction {
+        if let Some(outcome) = self.outcome {
+            return outcome;
+        }
+
+        // move on to the next stage once the sensor reads as awaited
+        loop {
+            let (awaited, next) = match self.stage {
+                HomingStage::LeaveIndex => (false, HomingStage::FindIndex),
+                HomingStage::FindIndex => (true, HomingStage::FindEdge),
+                HomingStage::FindEdge => (false, HomingStage::FindEdge),
+            };
+            if detected != awaited {
+                break;
+            }
+            if self.stage == HomingStage::FindEdge {
+                self.outcome = Some(HomingAction::Done);
+                return HomingAction::Done;
+            }
+            self.stage = next;
+            self.steps = 0;
+        }
+
+        let max_steps = match self.stage {
+            HomingStage::LeaveIndex => self.config.leave_max_steps,
+            HomingStage::FindIndex => self.config.find_max_steps,
+            HomingStage::FindEdge => self.config.edge_max_steps,
+        };
+        if self.steps >= max_steps {
+            let stage = self.stage;
+            let error = match detected {
+                true => HomingError::StuckHigh { stage },
+                false => HomingError::StuckLow { stage },
+            };
+
+            self.failures += 1;
+            if self.failures >= self.config.attempts {
+                self.outcome = Some(HomingAction::Failed(error));
+                return HomingAction::Failed(error);
+            }
+            // retry searching the other way round
+            self.direction = -self.direction;
+            self.stage = HomingStage::LeaveIndex;
+            self.steps = 0;
+            return self.next(detected);
+        }
+
+        let steps = match self.stage {
+            HomingStage::FindEdge => self.config.slow_steps,
+            _ => self.direction * self.config.fast_steps,
+        };
+        self.steps += steps.abs();
+        HomingAction::Step(steps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::{GlobeMotor, IndexSensor};
+    use crate::mock::{self, MockClock, MockDevices};
+
+    const INDEX_AT: i64 = 1000;
+    const INDEX_WIDTH: i64 = 40;
+
+    /// Run homing against mock hardware, return outcome and steps taken
+    fn run(devices: &mut MockDevices, config: HomingConfig) -> (HomingAction, i64) {
+        let mut homing = Homing::new(config);
+        let mut taken = 0;
+        loop {
+            match homing.next(devices.index.is_detected().unwrap()) {
+                HomingAction::Step(n) => {
+                    devices.motor.step(n).unwrap();
+                    taken += n.abs() as i64;
+                }
+                outcome => return (outcome, taken),
+            }
+        }
+    }
+
+    fn devices() -> MockDevices {
+        mock::devices(STEPS_PER_REV, INDEX_AT, INDEX_WIDTH, MockClock::unsynced())
+    }
+
+    #[test]
+    fn homes_on_trailing_edge() {
+        let mut devices = devices();
+        assert_eq!(
+            run(&mut devices, HomingConfig::DEFAULT).0,
+            HomingAction::Done
+        );
+        assert_eq!(devices.motor.position(), INDEX_AT + INDEX_WIDTH);
+
+        // starting on the index, the globe turns a whole revolution
+        devices.motor.step(-20).unwrap();
+        let (outcome, taken) = run(&mut devices, HomingConfig::DEFAULT);
+        assert_eq!(outcome, HomingAction::Done);
+        assert_eq!(
+            devices.motor.position(),
+            INDEX_AT + INDEX_WIDTH + STEPS_PER_REV as i64
+        );
+        assert!(taken > STEPS_PER_REV as i64);
+    }
+
+    #[test]
+    fn stuck_sensors_are_reported() {
+        let mut devices = devices();
+        devices.index.unplug();
+        let (outcome, taken) = run(&mut devices, HomingConfig::DEFAULT);
+        assert_eq!(
+            outcome,
+            HomingAction::Failed(HomingError::StuckLow {
+                stage: HomingStage::FindIndex
+            })
+        );
+        // every attempt gave up after its step budget
+        let budget = HomingConfig::DEFAULT.find_max_steps as i64;
+        assert!(taken <= 3 * budget + 30);
+
+        let mut devices = self::devices();
+        devices.index.short();
+        assert_eq!(
+            run(&mut devices, HomingConfig::DEFAULT).0,
+            HomingAction::Failed(HomingError::StuckHigh {
+                stage: HomingStage::LeaveIndex
+            })
+        );
+    }
+
+    #[test]
+    fn retries_the_other_way() {
+        // globe cannot turn forward past 500 steps
+        let mut devices = devices();
+        devices.motor.obstruct(-10_000..=500);
+
+        let mut homing = Homing::new(HomingConfig::DEFAULT);
+        let outcome = loop {
+            match homing.next(devices.index.is_detected().unwrap()) {
+                HomingAction::Step(n) => devices.motor.step(n).unwrap(),
+                outcome => break outcome,
+            }
+        };
+        assert_eq!(outcome, HomingAction::Done);
+        assert_eq!(homing.failures(), 1);
+        // same edge, one revolution backwards
+        assert_eq!(
+            devices.motor.position(),
+            INDEX_AT + INDEX_WIDTH - STEPS_PER_REV as i64
+        );
+    }
+
+    #[test]
+    fn outcome_is_sticky() {
+        let mut homing = Homing::new(HomingConfig {
+            attempts: 1,
+            ..Default::default()
+        });
+        assert_eq!(homing.next(false), HomingAction::Step(10));
+        assert_eq!(homing.next(true), HomingAction::Step(1));
+        assert_eq!(homing.stage(), HomingStage::FindEdge);
+        assert_eq!(homing.next(false), HomingAction::Done);
+        assert_eq!(homing.next(true), HomingAction::Done);
+    }
+}
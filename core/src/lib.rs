@@ -0,0 +1,27 @@
+//! Platform neutral moon globe application
+//!
+//! Hardware is reached through the traits of `hal`, implemented by the
+//! firmware on the ESP32 and by the `mock` module on the host.
+
+#![cfg_attr(not(test), no_std)]
+
+#[cfg(feature = "mock")]
+extern crate std;
+
+mod hal;
+pub use hal::{Backlight, GlobeMotor, IndexSensor, Storage, WallClock};
+
+mod position;
+pub use position::{Direction, Move, PositionConfig, Positioner, STEPS_PER_REV, ZERO_INDEX_OFFSET};
+
+mod homing;
+pub use homing::{Homing, HomingAction, HomingConfig, HomingError, HomingStage};
+
+mod stepper;
+pub use stepper::{Coils, Ramp, StepMode, Stepper, StepperConfig};
+
+mod app;
+pub use app::{Devices, Error, Moon, Status};
+
+#[cfg(feature = "mock")]
+pub mod mock;
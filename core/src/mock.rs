@@ -0,0 +1,246 @@
+//! Host implementation of the hardware traits, simulating a globe turned by
+//! an ideal motor in front of its index sensor
+
+use core::cell::Cell;
+use core::convert::Infallible;
+use core::ops::RangeInclusive;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::string::String;
+use std::vec::Vec;
+
+use crate::app::{Devices, Moon};
+use crate::hal::{Backlight, GlobeMotor, IndexSensor, Storage, WallClock};
+
+/// Mock hardware set
+pub type MockDevices = Devices<MockMotor, MockIndex, MockBacklight, MockClock, MockStorage>;
+/// Application running on mock hardware
+pub type MockMoon<'a, D> = Moon<'a, D, MockMotor, MockIndex, MockBacklight, MockClock, MockStorage>;
+
+/// Build a mock hardware set, the index flag covers `index_width` steps
+/// from `index_at` on each globe revolution of `globe_rev_steps` motor steps
+pub fn devices(
+    globe_rev_steps: i32,
+    index_at: i64,
+    index_width: i64,
+    clock: MockClock,
+) -> MockDevices {
+    let position = Rc::new(Cell::new(0));
+    Devices {
+        motor: MockMotor {
+            position: position.clone(),
+            energized: false,
+            range: i64::MIN..=i64::MAX,
+            background: false,
+            pending: 0,
+            release_pending: false,
+        },
+        index: MockIndex {
+            position,
+            globe_rev_steps: globe_rev_steps as i64,
+            at: index_at,
+            width: index_width,
+            stuck: None,
+        },
+        backlight: MockBacklight::default(),
+        clock,
+        storage: MockStorage::default(),
+    }
+}
+
+/// Motor never missing a step unless obstructed
+pub struct MockMotor {
+    position: Rc<Cell<i64>>,
+    energized: bool,
+    // positions the globe can reach
+    range: RangeInclusive<i64>,
+    // started moves wait for `finish`
+    background: bool,
+    // steps of started moves not taken yet
+    pending: i64,
+    release_pending: bool,
+}
+
+impl MockMotor {
+    /// Absolute globe position in steps since power on
+    pub fn position(&self) -> i64 {
+        self.position.get()
+    }
+
+    pub fn is_energized(&self) -> bool {
+        self.energized
+    }
+
+    /// Block globe outside provided absolute positions, steps beyond are
+    /// lost
+    pub fn obstruct(&mut self, range: RangeInclusive<i64>) {
+        self.range = range;
+    }
+
+    /// Keep started moves under way until `finish` is called, as a motor
+    /// driven from its own task would
+    pub fn run_in_background(&mut self) {
+        self.background = true;
+    }
+
+    /// Complete started moves, then release coils if asked meanwhile
+    pub fn finish(&mut self) {
+        let position = self.position.get() + core::mem::take(&mut self.pending);
+        self.position
+            .set(position.clamp(*self.range.start(), *self.range.end()));
+        if core::mem::take(&mut self.release_pending) {
+            self.energized = false;
+        }
+    }
+}
+
+impl GlobeMotor for MockMotor {
+    type Error = Infallible;
+
+    fn step(&mut self, steps: i32) -> Result<(), Infallible> {
+        self.pending += steps as i64;
+        self.release_pending = false;
+        self.energized = true;
+        self.finish();
+        Ok(())
+    }
+
+    fn start(&mut self, steps: i32) -> Result<(), Infallible> {
+        if !self.background {
+            return self.step(steps);
+        }
+        self.pending += steps as i64;
+        self.release_pending = false;
+        self.energized = true;
+        Ok(())
+    }
+
+    fn is_moving(&mut self) -> Result<bool, Infallible> {
+        Ok(self.pending != 0)
+    }
+
+    fn release(&mut self) -> Result<(), Infallible> {
+        if self.pending != 0 {
+            self.release_pending = true;
+        } else {
+            self.energized = false;
+        }
+        Ok(())
+    }
+}
+
+/// Index sensor reading the mock motor position
+pub struct MockIndex {
+    position: Rc<Cell<i64>>,
+    // motor steps per globe revolution
+    globe_rev_steps: i64,
+    at: i64,
+    width: i64,
+    // level the sensor is stuck at, if any
+    stuck: Option<bool>,
+}
+
+impl MockIndex {
+    /// Disconnect sensor, which then never detects the index
+    pub fn unplug(&mut self) {
+        self.stuck = Some(false);
+    }
+
+    /// Short sensor output, which then always detects the index
+    pub fn short(&mut self) {
+        self.stuck = Some(true);
+    }
+}
+
+impl IndexSensor for MockIndex {
+    type Error = Infallible;
+
+    fn is_detected(&mut self) -> Result<bool, Infallible> {
+        let offset = (self.position.get() - self.at).rem_euclid(self.globe_rev_steps);
+        Ok(self.stuck.unwrap_or(offset < self.width))
+    }
+}
+
+/// Backlight remembering its color
+#[derive(Default)]
+pub struct MockBacklight {
+    color: (u8, u8, u8),
+}
+
+impl MockBacklight {
+    pub fn color(&self) -> (u8, u8, u8) {
+        self.color
+    }
+}
+
+impl Backlight for MockBacklight {
+    type Error = Infallible;
+
+    fn set_color(&mut self, r: u8, g: u8, b: u8) -> Result<(), Infallible> {
+        self.color = (r, g, b);
+        Ok(())
+    }
+}
+
+/// Clock driven by the test, clones share the same time
+#[derive(Clone)]
+pub struct MockClock {
+    now: Rc<Cell<Option<i64>>>,
+}
+
+impl MockClock {
+    pub fn unsynced() -> Self {
+        MockClock {
+            now: Rc::new(Cell::new(None)),
+        }
+    }
+
+    pub fn at(unix: i64) -> Self {
+        MockClock {
+            now: Rc::new(Cell::new(Some(unix))),
+        }
+    }
+
+    pub fn set(&self, unix: Option<i64>) {
+        self.now.set(unix);
+    }
+
+    /// Move clock forward by provided number of seconds, if synchronized
+    pub fn advance(&self, seconds: i64) {
+        self.now.set(self.now.get().map(|t| t + seconds));
+    }
+}
+
+impl WallClock for MockClock {
+    fn now(&self) -> Option<i64> {
+        self.now.get()
+    }
+}
+
+/// In memory storage
+#[derive(Default)]
+pub struct MockStorage {
+    values: HashMap<String, Vec<u8>>,
+}
+
+impl Storage for MockStorage {
+    type Error = Infallible;
+
+    fn get(&mut self, key: &str, buf: &mut [u8]) -> Result<Option<usize>, Infallible> {
+        Ok(self.values.get(key).map(|v| {
+            let len = v.len().min(buf.len());
+            buf[..len].copy_from_slice(&v[..len]);
+            len
+        }))
+    }
+
+    fn set(&mut self, key: &str, value: &[u8]) -> Result<(), Infallible> {
+        self.values.insert(key.into(), value.into());
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), Infallible> {
+        self.values.remove(key);
+        Ok(())
+    }
+}
@@ -0,0 +1,317 @@
+use ephemeris::Centidegrees;
+
+/// Motor steps per motor shaft revolution, 28BYJ-48 driven in half steps
+pub const STEPS_PER_REV: i32 = 4096;
+/// Motor steps from the index trailing edge to a zero globe angle
+pub const ZERO_INDEX_OFFSET: i32 = 0;
+
+/// Centidegrees in a globe revolution
+const FULL_TURN: i64 = 36000;
+
+/// Directions the globe is allowed to turn in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// whichever direction reaches the target first
+    Shortest,
+    /// towards increasing angles only
+    Forward,
+    /// towards decreasing angles only
+    Backward,
+}
+
+/// Mechanical setup of the globe drive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PositionConfig {
+    // motor steps per motor shaft revolution
+    pub steps_per_rev: i32,
+    // motor shaft revolutions per globe revolution, as numerator and
+    // denominator
+    pub gear_ratio: (i32, i32),
+    // motor steps from the index trailing edge to a zero globe angle
+    pub zero_index_offset: i32,
+    pub direction: Direction,
+    // motor steps lost when the drive reverses
+    pub backlash: i32,
+}
+
+impl PositionConfig {
+    /// Globe directly on the motor shaft
+    pub const DEFAULT: PositionConfig = PositionConfig {
+        steps_per_rev: STEPS_PER_REV,
+        gear_ratio: (1, 1),
+        zero_index_offset: ZERO_INDEX_OFFSET,
+        direction: Direction::Shortest,
+        backlash: 0,
+    };
+
+    /// Motor steps per globe revolution, rounded to the closest step
+    pub fn globe_rev_steps(&self) -> i32 {
+        let (num, den) = (self.gear_ratio.0 as i64, self.gear_ratio.1 as i64);
+        div_round(self.steps_per_rev as i64 * num, den) as i32
+    }
+
+    /// Motor position in steps for provided unwrapped globe angle
+    fn steps(&self, angle: i64) -> i64 {
+        let (num, den) = (self.gear_ratio.0 as i64, self.gear_ratio.1 as i64);
+        self.zero_index_offset as i64
+            + div_round(angle * self.steps_per_rev as i64 * num, FULL_TURN * den)
+    }
+
+    /// Index trailing edge position closest to provided motor position
+    fn index_edge(&self, steps: i64) -> i64 {
+        let (num, den) = (self.gear_ratio.0 as i64, self.gear_ratio.1 as i64);
+        let rev = self.steps_per_rev as i64 * num;
+        div_round(div_round(steps * den, rev) * rev, den)
+    }
+
+    /// Unwrapped globe angle closest to provided motor position
+    fn angle(&self, steps: i64) -> i64 {
+        let (num, den) = (self.gear_ratio.0 as i64, self.gear_ratio.1 as i64);
+        div_round(
+            (steps - self.zero_index_offset as i64) * FULL_TURN * den,
+            self.steps_per_rev as i64 * num,
+        )
+    }
+}
+
+impl Default for PositionConfig {
+    fn default() -> Self {
+        PositionConfig::DEFAULT
+    }
+}
+
+/// Integer division rounding half away from zero
+fn div_round(a: i64, b: i64) -> i64 {
+    let q = a / b;
+    match 2 * (a % b).abs() >= b.abs() {
+        true => q + (a.signum() * b.signum()),
+        false => q,
+    }
+}
+
+/// Motor move planned by a `Positioner`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Move {
+    // steps turning the globe
+    pub delta: i64,
+    // steps taking up gear slack first, same sign as delta
+    pub slack: i64,
+}
+
+impl Move {
+    /// Signed number of motor steps to take
+    pub fn steps(&self) -> i64 {
+        self.delta + self.slack
+    }
+}
+
+/// Turns shadow angles into motor moves
+///
+/// Globe angle is kept unwrapped across revolutions and motor targets are
+/// derived from it, so rounding never accumulates however long the globe
+/// keeps turning.
+#[derive(Debug, Clone)]
+pub struct Positioner {
+    config: PositionConfig,
+    // unwrapped globe angle in centidegrees, None until first move
+    angle: Option<i64>,
+    // motor position in steps from the index, backlash excluded
+    position: i64,
+    // sign of the last motor move
+    last_direction: i64,
+}
+
+impl Positioner {
+    pub const fn new(config: PositionConfig) -> Self {
+        Positioner {
+            config,
+            angle: None,
+            position: 0,
+            last_direction: 0,
+        }
+    }
+
+    pub fn config(&self) -> &PositionConfig {
+        &self.config
+    }
+
+    /// Motor position in steps from the index
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+
+    /// Globe angle ranging [0;360[, None until first move
+    pub fn angle(&self) -> Option<Centidegrees> {
+        self.angle
+            .map(|a| Centidegrees(a.rem_euclid(FULL_TURN) as i32))
+    }
+
+    /// Sign of the last motor move, gear slack is taken up that way
+    pub fn last_direction(&self) -> i64 {
+        self.last_direction
+    }
+
+    /// Restart from the index, reached while turning forward
+    pub fn reset(&mut self) {
+        self.restore(0, 1);
+    }
+
+    /// Restart from a previously known motor position
+    pub fn restore(&mut self, position: i64, last_direction: i64) {
+        self.angle = None;
+        self.position = position;
+        self.last_direction = last_direction.signum();
+    }
+
+    /// Account for the index trailing edge being seen at provided motor
+    /// position, return the position error that got corrected
+    ///
+    /// Globe is considered off by a whole number of steps, the next move
+    /// brings it back on target.
+    pub fn index_seen(&mut self, position: i64) -> i64 {
+        let error = position - self.config.index_edge(position);
+        self.position -= error;
+        error
+    }
+
+    /// Plan a move to provided globe angle
+    pub fn move_to(&mut self, angle: Centidegrees) -> Move {
+        let current = self
+            .angle
+            .unwrap_or_else(|| self.config.angle(self.position));
+
+        let diff = (angle.0 as i64 - current).rem_euclid(FULL_TURN);
+        let diff = match self.config.direction {
+            Direction::Forward => diff,
+            Direction::Backward if diff == 0 => 0,
+            Direction::Backward => diff - FULL_TURN,
+            Direction::Shortest if diff >= FULL_TURN / 2 => diff - FULL_TURN,
+            Direction::Shortest => diff,
+        };
+        let target = current + diff;
+        self.angle = Some(target);
+
+        let delta = self.config.steps(target) - self.position;
+        self.position += delta;
+        if delta == 0 {
+            return Move::default();
+        }
+
+        // take gear slack up first when reversing
+        let direction = delta.signum();
+        let slack = match direction == -self.last_direction {
+            true => direction * self.config.backlash as i64,
+            false => 0,
+        };
+        self.last_direction = direction;
+        Move { delta, slack }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positioner(config: PositionConfig) -> Positioner {
+        let mut p = Positioner::new(config);
+        p.reset();
+        p
+    }
+
+    #[test]
+    fn angle_to_steps() {
+        let mut p = positioner(PositionConfig {
+            gear_ratio: (3, 1),
+            zero_index_offset: 100,
+            ..Default::default()
+        });
+        // globe turns three times slower than the motor
+        assert_eq!(p.move_to(Centidegrees(9000)).steps(), 100 + 3 * 1024);
+        assert_eq!(p.position(), 100 + 3 * 1024);
+        assert_eq!(p.angle(), Some(Centidegrees(9000)));
+        assert_eq!(p.move_to(Centidegrees(9000)).steps(), 0);
+    }
+
+    #[test]
+    fn shortest_path_wraps() {
+        let mut p = positioner(PositionConfig::DEFAULT);
+        assert_eq!(p.move_to(Centidegrees(1000)).steps(), 114);
+        // crossing zero backwards instead of turning a whole revolution
+        assert_eq!(p.move_to(Centidegrees(35000)).steps(), -228);
+        assert_eq!(p.position(), -114);
+        assert_eq!(p.angle(), Some(Centidegrees(35000)));
+    }
+
+    #[test]
+    fn one_direction_only() {
+        let mut p = positioner(PositionConfig {
+            direction: Direction::Backward,
+            ..Default::default()
+        });
+        let mut angle = 0;
+        for _ in 0..100 {
+            angle = (angle + 1000) % 36000;
+            assert!(p.move_to(Centidegrees(angle)).steps() <= 0);
+        }
+
+        p.reset();
+        p.config.direction = Direction::Forward;
+        for a in [30000, 1000, 20000, 20000] {
+            assert!(p.move_to(Centidegrees(a)).steps() >= 0);
+        }
+    }
+
+    #[test]
+    fn no_drift_across_wraps() {
+        // 4096 steps do not split evenly in 7 degree moves on that gear
+        let config = PositionConfig {
+            gear_ratio: (3, 2),
+            direction: Direction::Backward,
+            ..Default::default()
+        };
+        let mut p = positioner(config);
+        let mut travel = 0;
+        for i in 1..=600 {
+            travel += p
+                .move_to(Centidegrees((36000 - 700 * i % 36000) % 36000))
+                .steps();
+        }
+        // about 11 revolutions backwards
+        let expected = config.steps(-600 * 700);
+        assert_eq!(travel, expected);
+        assert_eq!(p.position(), expected);
+    }
+
+    #[test]
+    fn backlash_on_reversal() {
+        let mut p = positioner(PositionConfig {
+            backlash: 12,
+            ..Default::default()
+        });
+        // homing ended turning forward
+        assert_eq!(p.move_to(Centidegrees(1000)).steps(), 114);
+        assert_eq!(p.move_to(Centidegrees(500)).steps(), -57 - 12);
+        assert_eq!(p.move_to(Centidegrees(0)).steps(), -57);
+        assert_eq!(p.move_to(Centidegrees(1000)).steps(), 114 + 12);
+        // slack is not part of the position
+        assert_eq!(p.position(), 114);
+    }
+
+    #[test]
+    fn index_corrects_position() {
+        let config = PositionConfig {
+            gear_ratio: (3, 2),
+            ..Default::default()
+        };
+        let mut p = positioner(config);
+        p.restore(1000, -1);
+        p.move_to(Centidegrees(0));
+        assert_eq!(p.position(), 0);
+
+        // one globe revolution is 6144 steps, edge seen 5 steps late
+        assert_eq!(p.index_seen(-6144 - 5), -5);
+        assert_eq!(p.position(), 5);
+        assert_eq!(p.move_to(Centidegrees(0)).steps(), -5);
+        assert_eq!(p.index_seen(0), 0);
+    }
+}
@@ -0,0 +1,433 @@
+use libm::sqrtf;
+
+/// Coil patterns of a 4 wire unipolar motor such as the 28BYJ-48, coils
+/// listed in driver input order
+pub type Coils = [bool; 4];
+
+/// Two coils energized at once, full torque
+const FULL_STEP: [Coils; 4] = [
+    [true, true, false, false],
+    [false, true, true, false],
+    [false, false, true, true],
+    [true, false, false, true],
+];
+/// One and two coils energized in turn, twice the resolution
+const HALF_STEP: [Coils; 8] = [
+    [true, false, false, false],
+    [true, true, false, false],
+    [false, true, false, false],
+    [false, true, true, false],
+    [false, false, true, false],
+    [false, false, true, true],
+    [false, false, false, true],
+    [true, false, false, true],
+];
+
+/// Coil sequences
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepMode {
+    FullStep,
+    HalfStep,
+}
+
+impl StepMode {
+    fn sequence(self) -> &'static [Coils] {
+        match self {
+            StepMode::FullStep => &FULL_STEP,
+            StepMode::HalfStep => &HALF_STEP,
+        }
+    }
+
+    /// Steps per output shaft revolution of a 28BYJ-48
+    pub const fn steps_per_rev(self) -> i32 {
+        match self {
+            StepMode::FullStep => 2048,
+            StepMode::HalfStep => 4096,
+        }
+    }
+}
+
+/// Speed profiles while accelerating and braking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ramp {
+    /// constant acceleration
+    Trapezoidal,
+    /// acceleration grows then fades, gentler on heavy globes
+    SCurve,
+}
+
+/// Lowest start speed and acceleration moves run with, zero or NaN would
+/// stall the motor on an infinite step interval
+const MIN_RATE: f32 = 1.0;
+
+/// Stepper motion settings, speeds are in steps per second
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepperConfig {
+    pub mode: StepMode,
+    pub ramp: Ramp,
+    // speed moves start and end at, raised to 1 when lower
+    pub start_speed: f32,
+    pub max_speed: f32,
+    // steps per second squared, raised to 1 when lower
+    pub acceleration: f32,
+}
+
+impl StepperConfig {
+    /// 28BYJ-48 in half steps carrying the globe
+    pub const DEFAULT: StepperConfig = StepperConfig {
+        mode: StepMode::HalfStep,
+        ramp: Ramp::SCurve,
+        start_speed: 100.0,
+        max_speed: 500.0,
+        acceleration: 1000.0,
+    };
+
+    /// Start and max speeds moves actually run with
+    fn speeds(&self) -> (f32, f32) {
+        // max ignores NaN
+        let v0 = self.start_speed.max(MIN_RATE);
+        (v0, self.max_speed.max(v0))
+    }
+
+    /// Steps taken to go from start speed to max speed
+    fn ramp_steps(&self) -> u32 {
+        let (v0, v1) = self.speeds();
+        ((v1 * v1 - v0 * v0) / (2.0 * self.acceleration.max(MIN_RATE))) as u32
+    }
+
+    /// Speed reached after provided number of ramp steps
+    fn speed(&self, ramp: u32) -> f32 {
+        let (v0, v1) = self.speeds();
+        let len = self.ramp_steps();
+        if ramp >= len {
+            return v1;
+        }
+
+        let x = ramp as f32 / len as f32;
+        match self.ramp {
+            Ramp::Trapezoidal => sqrtf(v0 * v0 + (v1 * v1 - v0 * v0) * x),
+            Ramp::SCurve => v0 + (v1 - v0) * x * x * (3.0 - 2.0 * x),
+        }
+    }
+}
+
+impl Default for StepperConfig {
+    fn default() -> Self {
+        StepperConfig::DEFAULT
+    }
+}
+
+/// Non-blocking stepper motion generator
+///
+/// `move_to` only sets a target, `poll` takes one step whenever it is due
+/// and is meant to be called from a timer or a dedicated task which then
+/// writes `coils` to the driver.
+#[derive(Debug, Clone)]
+pub struct Stepper {
+    config: StepperConfig,
+    position: i64,
+    target: i64,
+    // steps into the acceleration ramp, also the steps needed to stop
+    ramp: u32,
+    // direction of the current motion
+    direction: i64,
+    // time of the next step in microseconds
+    next_step_us: u64,
+    energized: bool,
+    // coils are released once the motion under way ends
+    release_pending: bool,
+}
+
+impl Stepper {
+    pub const fn new(config: StepperConfig) -> Self {
+        Stepper {
+            config,
+            position: 0,
+            target: 0,
+            ramp: 0,
+            direction: 0,
+            next_step_us: 0,
+            energized: false,
+            release_pending: false,
+        }
+    }
+
+    pub fn config(&self) -> &StepperConfig {
+        &self.config
+    }
+
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+
+    pub fn target(&self) -> i64 {
+        self.target
+    }
+
+    /// Current speed in steps per second, signed by direction
+    pub fn speed(&self) -> f32 {
+        match self.direction {
+            0 => 0.0,
+            d => d as f32 * self.config.speed(self.ramp),
+        }
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.direction != 0 || self.position != self.target
+    }
+
+    /// Head for provided position, a motion under way brakes first if it
+    /// has to reverse
+    ///
+    /// Cancels a pending `release`.
+    pub fn move_to(&mut self, target: i64) {
+        self.target = target;
+        self.release_pending = false;
+    }
+
+    /// Head for a position relative to the current target
+    ///
+    /// Cancels a pending `release`.
+    pub fn move_by(&mut self, steps: i64) {
+        self.move_to(self.target + steps);
+    }
+
+    /// Time of the next step or pending release in microseconds, None when
+    /// idle
+    pub fn next_step_at(&self) -> Option<u64> {
+        (self.is_moving() || self.release_pending).then_some(self.next_step_us)
+    }
+
+    /// Coils to energize
+    pub fn coils(&self) -> Coils {
+        match self.energized {
+            true => {
+                let sequence = self.config.mode.sequence();
+                sequence[self.position.rem_euclid(sequence.len() as i64) as usize]
+            }
+            false => [false; 4],
+        }
+    }
+
+    /// Stop driving the coils, right away when idle or else one step
+    /// interval after the motion under way ends
+    pub fn release(&mut self) {
+        if self.is_moving() {
+            self.release_pending = true;
+        } else {
+            self.energized = false;
+        }
+    }
+
+    /// Take a step if one is due at provided time in microseconds, return
+    /// true if coils changed
+    pub fn poll(&mut self, now_us: u64) -> bool {
+        if !self.is_moving() {
+            // coils held the last step for an interval, let them go
+            if self.release_pending && now_us >= self.next_step_us {
+                self.release_pending = false;
+                self.energized = false;
+                return true;
+            }
+            return false;
+        }
+        if self.direction == 0 {
+            // motion starts now
+            self.next_step_us = now_us;
+        }
+        if now_us < self.next_step_us {
+            return false;
+        }
+
+        let remaining = self.target - self.position;
+        if remaining == 0 && self.ramp == 0 {
+            self.direction = 0;
+            return false;
+        }
+        if self.direction == 0 {
+            self.direction = remaining.signum();
+        }
+
+        // brake when heading the wrong way or just close enough to stop on
+        // target, speed up while there is room to brake afterwards
+        let ahead = remaining * self.direction;
+        let ramp = self.ramp as i64;
+        if ahead <= 0 && ramp == 0 {
+            // stopped past target, head back
+            self.direction = remaining.signum();
+        } else if ahead <= ramp {
+            self.ramp -= 1;
+        } else if ahead > ramp + 1 && self.ramp < self.config.ramp_steps() {
+            self.ramp += 1;
+        }
+
+        self.position += self.direction;
+        self.energized = true;
+        if self.position == self.target && self.ramp == 0 {
+            self.direction = 0;
+        }
+
+        // keep to schedule across late polls, unless running far behind
+        let interval = 1e6 / self.config.speed(self.ramp);
+        self.next_step_us = self
+            .next_step_us
+            .max(now_us.saturating_sub(1000))
+            .saturating_add(interval as u64);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run stepper until idle, return step times
+    fn run(stepper: &mut Stepper, mut now: u64) -> std::vec::Vec<u64> {
+        let mut times = std::vec::Vec::new();
+        while let Some(t) = stepper.next_step_at() {
+            now = now.max(t);
+            if stepper.poll(now) {
+                times.push(now);
+            }
+            assert!(times.len() < 100_000);
+        }
+        times
+    }
+
+    #[test]
+    fn sequences() {
+        let mut stepper = Stepper::new(StepperConfig {
+            mode: StepMode::FullStep,
+            ..Default::default()
+        });
+        assert_eq!(stepper.coils(), [false; 4]);
+
+        stepper.move_to(5);
+        let mut seen = std::vec::Vec::new();
+        while let Some(t) = stepper.next_step_at() {
+            if stepper.poll(t) {
+                seen.push(stepper.coils());
+            }
+        }
+        assert_eq!(
+            seen,
+            [
+                FULL_STEP[1],
+                FULL_STEP[2],
+                FULL_STEP[3],
+                FULL_STEP[0],
+                FULL_STEP[1]
+            ]
+        );
+
+        // going backwards replays the sequence in reverse
+        stepper.move_by(-2);
+        run(&mut stepper, 0);
+        assert_eq!(stepper.coils(), FULL_STEP[3]);
+
+        stepper.release();
+        assert_eq!(stepper.coils(), [false; 4]);
+        assert_eq!(StepMode::HalfStep.sequence()[1], [true, true, false, false]);
+    }
+
+    #[test]
+    fn ramps_up_and_down() {
+        for ramp in [Ramp::Trapezoidal, Ramp::SCurve] {
+            let config = StepperConfig {
+                ramp,
+                ..Default::default()
+            };
+            let mut stepper = Stepper::new(config);
+            stepper.move_to(2000);
+            let times = run(&mut stepper, 0);
+            assert_eq!(times.len(), 2000);
+            assert_eq!(stepper.position(), 2000);
+
+            let interval = |i: usize| (times[i + 1] - times[i]) as f32;
+            // slow at both ends, cruising at max speed in between
+            assert!(interval(0) > 1e6 / 150.0);
+            assert!(interval(1997) > 1e6 / 150.0);
+            assert!((interval(1000) - 1e6 / 500.0).abs() < 10.0);
+            // speed never jumps
+            for i in 0..times.len() - 2 {
+                assert!(
+                    (interval(i + 1) / interval(i) - 1.0).abs() < 0.1,
+                    "{ramp:?} {i}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn retarget_brakes_before_reversing() {
+        let mut stepper = Stepper::new(StepperConfig::DEFAULT);
+        stepper.move_to(1000);
+        while stepper.position() < 300 {
+            stepper.poll(stepper.next_step_at().unwrap());
+        }
+        assert!(stepper.speed() > 400.0);
+
+        stepper.move_to(0);
+        let mut furthest = 0;
+        while let Some(t) = stepper.next_step_at() {
+            stepper.poll(t);
+            furthest = furthest.max(stepper.position());
+        }
+        // overshoot is the braking distance
+        let braking = StepperConfig::DEFAULT.ramp_steps() as i64;
+        assert!(furthest > 300 && furthest <= 300 + braking);
+        assert_eq!(stepper.position(), 0);
+        assert!(!stepper.is_moving());
+    }
+
+    #[test]
+    fn release_waits_for_motion_end() {
+        let mut stepper = Stepper::new(StepperConfig::DEFAULT);
+        stepper.move_to(10);
+        for _ in 0..3 {
+            stepper.poll(stepper.next_step_at().unwrap());
+        }
+        stepper.release();
+        assert_ne!(stepper.coils(), [false; 4]);
+
+        // last step is held for an interval before coils are let go
+        let times = run(&mut stepper, 0);
+        assert_eq!(stepper.position(), 10);
+        assert_eq!(times.len(), 7 + 1);
+        assert!(times[7] > times[6]);
+        assert_eq!(stepper.coils(), [false; 4]);
+        assert_eq!(stepper.next_step_at(), None);
+
+        // a new target cancels a pending release
+        stepper.move_to(20);
+        stepper.poll(stepper.next_step_at().unwrap());
+        stepper.release();
+        stepper.move_by(5);
+        run(&mut stepper, 0);
+        assert_eq!(stepper.position(), 25);
+        assert_ne!(stepper.coils(), [false; 4]);
+    }
+
+    #[test]
+    fn degenerate_configs_still_move() {
+        for (start_speed, acceleration) in [
+            (0.0, 1000.0),
+            (100.0, 0.0),
+            (0.0, 0.0),
+            (-5.0, -5.0),
+            (f32::NAN, f32::NAN),
+        ] {
+            let mut stepper = Stepper::new(StepperConfig {
+                start_speed,
+                acceleration,
+                ..Default::default()
+            });
+            assert!(stepper.config.speed(0) >= MIN_RATE);
+
+            stepper.move_to(20);
+            let times = run(&mut stepper, 0);
+            assert_eq!(times.len(), 20, "{start_speed} {acceleration}");
+            assert_eq!(stepper.position(), 20);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Generate the bundled ephemeris table when the `generated-data` feature
+//! is enabled.
+//!
+//! Without it the checked-in `src/data.rs` is used, which is this very table
+//! for `ephemeris.conf`, a test makes sure it stays so.
+//!
+//! Table is computed from the configuration found in `ephemeris.conf`, or in
+//! the file pointed by `MOON_EPHEMERIS_CONFIG`, using the crate own analytic
+//! models. Those only rely on libm so the same configuration always yields
+//! the very same table. Generation fails if the table breaks one of the
+//! sanity rules of `src/rules.rs`. Configuration format and generation live
+//! in `src/generate.rs` so the tests cover them.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+#[path = "src/generate.rs"]
+mod generate;
+#[allow(dead_code)]
+#[path = "src/lunar.rs"]
+mod lunar;
+#[allow(dead_code)]
+#[path = "src/observer.rs"]
+mod observer;
+#[allow(dead_code)]
+#[path = "src/rules.rs"]
+mod rules;
+#[allow(dead_code)]
+#[path = "src/solar.rs"]
+mod solar;
+#[allow(dead_code)]
+#[path = "src/time.rs"]
+mod time;
+#[allow(dead_code)]
+#[path = "src/units.rs"]
+mod units;
+
+use generate::{compute, render, Config};
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=MOON_EPHEMERIS_CONFIG");
+    if env::var_os("CARGO_FEATURE_GENERATED_DATA").is_none() {
+        return;
+    }
+
+    let config_path = env::var_os("MOON_EPHEMERIS_CONFIG")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("ephemeris.conf"));
+    for path in [
+        config_path.as_path(),
+        "src/generate.rs".as_ref(),
+        "src/lunar.rs".as_ref(),
+        "src/solar.rs".as_ref(),
+        "src/observer.rs".as_ref(),
+        "src/rules.rs".as_ref(),
+        "src/time.rs".as_ref(),
+        "src/units.rs".as_ref(),
+    ] {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+
+    let text = fs::read_to_string(&config_path)
+        .unwrap_or_else(|e| panic!("cannot read {}: {e}", config_path.display()));
+    let config =
+        Config::parse(&text).unwrap_or_else(|e| panic!("invalid {}: {e}", config_path.display()));
+
+    let (shadow, elevation) =
+        compute(&config).unwrap_or_else(|v| panic!("refusing to generate invalid table, {v}"));
+
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("data.rs");
+    fs::write(&out, render(&config, &shadow, &elevation)).unwrap();
+}
@@ -0,0 +1,537 @@
+//! Versioned binary ephemeris format
+//!
+//! A blob is a little endian byte buffer laid out as follows:
+//!
+//! | offset | size | field                                        |
+//! |--------|------|----------------------------------------------|
+//! | 0      | 4    | magic `MOON`                                 |
+//! | 4      | 2    | format version (`BLOB_VERSION`)              |
+//! | 6      | 2    | header size in bytes (`HEADER_LEN`)          |
+//! | 8      | 8    | starting unix timestamp                      |
+//! | 16     | 4    | time between each entry in seconds           |
+//! | 20     | 4    | observer latitude in microdegrees (i32)      |
+//! | 24     | 4    | observer longitude in microdegrees (i32)     |
+//! | 28     | 4    | observer height in metres (i32)              |
+//! | 32     | 16   | shadow channel descriptor                    |
+//! | 48     | 16   | elevation channel descriptor                 |
+//! | 64     | ...  | channel payloads                             |
+//! | end-4  | 4    | CRC-32 (IEEE) of every preceding byte        |
+//!
+//! A channel descriptor is made of:
+//!
+//! | offset | size | field                                        |
+//! |--------|------|----------------------------------------------|
+//! | 0      | 1    | sample encoding (`SampleEncoding`)           |
+//! | 1      | 1    | reserved, zero                               |
+//! | 2      | 2    | wrapping modulus, zero if channel is linear  |
+//! | 4      | 4    | number of samples                            |
+//! | 8      | 4    | payload offset from the start of the blob    |
+//! | 12     | 4    | payload size in bytes                        |
+//!
+//! Raw payloads hold one little endian sample after the other. Compact
+//! payloads hold one `Block` header every `BLOCK_LEN` samples followed by
+//! the packed bits of a `CompactChannel`.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::compact::{Block, CompactChannel, BLOCK_LEN, MAX_WIDTH};
+#[cfg(feature = "alloc")]
+use crate::compact::{CompactChannelBuf, SHADOW_MODULUS};
+use crate::defs::{Channel, Ephemeris};
+use crate::observer::Observer;
+
+/// Blob magic
+pub const BLOB_MAGIC: [u8; 4] = *b"MOON";
+/// Current format version
+pub const BLOB_VERSION: u16 = 1;
+/// Size of the blob header in bytes
+pub const HEADER_LEN: usize = 64;
+
+const DESCRIPTOR_LEN: usize = 16;
+const CRC_LEN: usize = 4;
+
+/// Location the elevation channel was computed for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Location {
+    // latitude in microdegrees
+    pub latitude: i32,
+    // longitude in microdegrees, positive east
+    pub longitude: i32,
+    // height above sea level in metres
+    pub height: i32,
+}
+
+impl From<Location> for Observer {
+    fn from(l: Location) -> Self {
+        Observer {
+            latitude: l.latitude as f64 / 1e6,
+            longitude: l.longitude as f64 / 1e6,
+            height: l.height as f64,
+        }
+    }
+}
+
+/// How samples of a channel are stored in a blob
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SampleEncoding {
+    /// one little endian u16 per sample
+    RawU16 = 0,
+    /// one i8 per sample
+    RawI8 = 1,
+    /// `CompactChannel` block headers followed by packed bits
+    Compact = 2,
+}
+
+impl TryFrom<u8> for SampleEncoding {
+    type Error = BlobError;
+
+    fn try_from(v: u8) -> Result<Self, BlobError> {
+        match v {
+            0 => Ok(SampleEncoding::RawU16),
+            1 => Ok(SampleEncoding::RawI8),
+            2 => Ok(SampleEncoding::Compact),
+            _ => Err(BlobError::UnknownEncoding(v)),
+        }
+    }
+}
+
+/// Reasons a blob can be rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlobError {
+    /// buffer is too short to hold a header and a checksum
+    TooShort,
+    /// buffer does not start with `BLOB_MAGIC`
+    BadMagic,
+    /// blob was written by an unknown format version
+    UnsupportedVersion(u16),
+    /// stored checksum does not match blob content
+    BadChecksum,
+    /// channel uses an unknown sample encoding
+    UnknownEncoding(u8),
+    /// channel payload lies outside of the blob, has the wrong size or
+    /// declares a bit width compact channels cannot hold
+    BadPayload,
+}
+
+impl fmt::Display for BlobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlobError::TooShort => write!(f, "blob is too short"),
+            BlobError::BadMagic => write!(f, "blob magic mismatch"),
+            BlobError::UnsupportedVersion(v) => write!(f, "unsupported blob version {v}"),
+            BlobError::BadChecksum => write!(f, "blob checksum mismatch"),
+            BlobError::UnknownEncoding(e) => write!(f, "unknown sample encoding {e}"),
+            BlobError::BadPayload => write!(f, "malformed channel payload"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BlobError {}
+
+/// Channel borrowed from a blob
+#[derive(Debug, Clone, Copy)]
+pub enum BlobChannel<'a> {
+    RawU16(&'a [u8]),
+    RawI8(&'a [u8]),
+    Compact(CompactChannel<'a>),
+}
+
+impl Channel for BlobChannel<'_> {
+    fn len(&self) -> usize {
+        match self {
+            BlobChannel::RawU16(b) => b.len() / 2,
+            BlobChannel::RawI8(b) => b.len(),
+            BlobChannel::Compact(c) => c.len(),
+        }
+    }
+
+    fn sample(&self, index: usize) -> Option<i32> {
+        match self {
+            BlobChannel::RawU16(b) => {
+                let s = b.get(2 * index..2 * index + 2)?;
+                Some(u16::from_le_bytes([s[0], s[1]]) as i32)
+            }
+            BlobChannel::RawI8(b) => b.get(index).map(|&v| v as i8 as i32),
+            BlobChannel::Compact(c) => c.sample(index),
+        }
+    }
+}
+
+/// Zero-copy view of an ephemeris blob
+#[derive(Debug, Clone, Copy)]
+pub struct EphemerisBlob<'a> {
+    // starting unix timestamp
+    pub start: u64,
+    // time between each entry in seconds
+    pub period: u32,
+    // location elevation was computed for
+    pub location: Location,
+    // moon shadow angles in decidegrees
+    pub shadow: BlobChannel<'a>,
+    // moon elevation angle in degrees
+    pub elevation: BlobChannel<'a>,
+}
+
+impl<'a> EphemerisBlob<'a> {
+    /// Check and parse provided blob without copying its samples
+    pub fn parse(blob: &'a [u8]) -> Result<Self, BlobError> {
+        if blob.len() < HEADER_LEN + CRC_LEN {
+            return Err(BlobError::TooShort);
+        }
+        if blob[0..4] != BLOB_MAGIC {
+            return Err(BlobError::BadMagic);
+        }
+        let version = read_u16(blob, 4);
+        if version != BLOB_VERSION {
+            return Err(BlobError::UnsupportedVersion(version));
+        }
+
+        // checksum covers everything but the checksum itself
+        let (content, crc) = blob.split_at(blob.len() - CRC_LEN);
+        if crc32(content) != read_u32(crc, 0) {
+            return Err(BlobError::BadChecksum);
+        }
+
+        let header_len = read_u16(blob, 6) as usize;
+        if header_len < HEADER_LEN || header_len > content.len() {
+            return Err(BlobError::BadPayload);
+        }
+
+        Ok(EphemerisBlob {
+            start: read_u64(blob, 8),
+            period: read_u32(blob, 16),
+            location: Location {
+                latitude: read_u32(blob, 20) as i32,
+                longitude: read_u32(blob, 24) as i32,
+                height: read_u32(blob, 28) as i32,
+            },
+            shadow: parse_channel(content, 32)?,
+            elevation: parse_channel(content, 32 + DESCRIPTOR_LEN)?,
+        })
+    }
+}
+
+impl<'a> Ephemeris for EphemerisBlob<'a> {
+    type Shadow = BlobChannel<'a>;
+    type Elevation = BlobChannel<'a>;
+
+    fn start(&self) -> u64 {
+        self.start
+    }
+
+    fn period(&self) -> u32 {
+        self.period
+    }
+
+    fn shadow(&self) -> &BlobChannel<'a> {
+        &self.shadow
+    }
+
+    fn elevation(&self) -> &BlobChannel<'a> {
+        &self.elevation
+    }
+}
+
+fn parse_channel(content: &[u8], at: usize) -> Result<BlobChannel<'_>, BlobError> {
+    let encoding = SampleEncoding::try_from(content[at])?;
+    let modulus = read_u16(content, at + 2);
+    let count = read_u32(content, at + 4);
+    let offset = read_u32(content, at + 8) as usize;
+    let size = read_u32(content, at + 12) as usize;
+
+    let payload = offset
+        .checked_add(size)
+        .and_then(|end| content.get(offset..end))
+        .ok_or(BlobError::BadPayload)?;
+
+    // sizes derived from crafted counts may not fit a 32 bits usize
+    let count = count as usize;
+    match encoding {
+        SampleEncoding::RawU16 if count.checked_mul(2) == Some(payload.len()) => {
+            Ok(BlobChannel::RawU16(payload))
+        }
+        SampleEncoding::RawI8 if payload.len() == count => Ok(BlobChannel::RawI8(payload)),
+        SampleEncoding::Compact => {
+            let headers_len = count
+                .div_ceil(BLOCK_LEN)
+                .checked_mul(Block::SIZE)
+                .ok_or(BlobError::BadPayload)?;
+            if payload.len() < headers_len {
+                return Err(BlobError::BadPayload);
+            }
+            let (blocks, bits) = payload.split_at(headers_len);
+            // bit widths come from the blob, reject any reads cannot handle
+            if blocks
+                .chunks(Block::SIZE)
+                .any(|b| Block::read(b, 0).map_or(true, |b| b.width > MAX_WIDTH))
+            {
+                return Err(BlobError::BadPayload);
+            }
+            Ok(BlobChannel::Compact(CompactChannel {
+                len: count as u32,
+                modulus,
+                blocks,
+                bits,
+            }))
+        }
+        _ => Err(BlobError::BadPayload),
+    }
+}
+
+#[cfg(feature = "alloc")]
+/// Serialize provided ephemeris as a blob
+///
+/// Return None if a sample does not fit the requested encoding.
+pub fn write_blob<E: Ephemeris + ?Sized>(
+    data: &E,
+    location: Location,
+    compact: bool,
+) -> Option<Vec<u8>> {
+    let mut blob = Vec::new();
+    blob.extend_from_slice(&BLOB_MAGIC);
+    blob.extend_from_slice(&BLOB_VERSION.to_le_bytes());
+    blob.extend_from_slice(&(HEADER_LEN as u16).to_le_bytes());
+    blob.extend_from_slice(&data.start().to_le_bytes());
+    blob.extend_from_slice(&data.period().to_le_bytes());
+    blob.extend_from_slice(&location.latitude.to_le_bytes());
+    blob.extend_from_slice(&location.longitude.to_le_bytes());
+    blob.extend_from_slice(&location.height.to_le_bytes());
+    // descriptors are filled once payloads are written
+    blob.resize(HEADER_LEN, 0);
+
+    write_channel(&mut blob, 0, data.shadow(), SHADOW_MODULUS, compact)?;
+    write_channel(&mut blob, 1, data.elevation(), 0, compact)?;
+
+    let crc = crc32(&blob);
+    blob.extend_from_slice(&crc.to_le_bytes());
+
+    Some(blob)
+}
+
+/// Append channel payload to blob and fill its descriptor
+#[cfg(feature = "alloc")]
+fn write_channel<C: Channel + ?Sized>(
+    blob: &mut Vec<u8>,
+    index: usize,
+    channel: &C,
+    modulus: u16,
+    compact: bool,
+) -> Option<()> {
+    let offset = blob.len();
+    let encoding = if compact {
+        let buf = CompactChannelBuf::encode(channel, modulus)?;
+        let encoded = buf.as_channel();
+        blob.extend_from_slice(encoded.blocks);
+        blob.extend_from_slice(encoded.bits);
+        SampleEncoding::Compact
+    } else if modulus != 0 {
+        // wrapping channels hold shadow angles stored as u16
+        for i in 0..channel.len() {
+            let v = u16::try_from(channel.sample(i)?).ok()?;
+            blob.extend_from_slice(&v.to_le_bytes());
+        }
+        SampleEncoding::RawU16
+    } else {
+        for i in 0..channel.len() {
+            let v = i8::try_from(channel.sample(i)?).ok()?;
+            blob.push(v as u8);
+        }
+        SampleEncoding::RawI8
+    };
+
+    let count = u32::try_from(channel.len()).ok()?;
+    let size = u32::try_from(blob.len() - offset).ok()?;
+    let offset = u32::try_from(offset).ok()?;
+
+    let at = 32 + index * DESCRIPTOR_LEN;
+    let descriptor = &mut blob[at..at + DESCRIPTOR_LEN];
+    descriptor[0] = encoding as u8;
+    descriptor[2..4].copy_from_slice(&modulus.to_le_bytes());
+    descriptor[4..8].copy_from_slice(&count.to_le_bytes());
+    descriptor[8..12].copy_from_slice(&offset.to_le_bytes());
+    descriptor[12..16].copy_from_slice(&size.to_le_bytes());
+
+    Some(())
+}
+
+fn read_u16(b: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([b[at], b[at + 1]])
+}
+
+fn read_u32(b: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]])
+}
+
+fn read_u64(b: &[u8], at: usize) -> u64 {
+    (read_u32(b, at) as u64) | (read_u32(b, at + 4) as u64) << 32
+}
+
+/// CRC-32 lookup table (IEEE 802.3, reflected polynomial)
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+/// Compute CRC-32 (IEEE) of provided bytes
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |c, &b| {
+        CRC_TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        elevation_from_unix_timestamp, shadow_angle_from_unix_timestamp, MoonEphemeris,
+        MOON_EPHEMERIS,
+    };
+
+    const EPHEMERIS: MoonEphemeris = MoonEphemeris {
+        start: 1234567,
+        period: 3600,
+        shadow: &[3500, 100, 900, 1800],
+        elevation: &[-12, 0, 45, 12],
+    };
+
+    const LOCATION: Location = Location {
+        latitude: 44_850_000,
+        longitude: -550_000,
+        height: 12,
+    };
+
+    #[test]
+    fn crc_known_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn roundtrip() {
+        for compact in [false, true] {
+            let bytes = write_blob(&EPHEMERIS, LOCATION, compact).unwrap();
+            let blob = EphemerisBlob::parse(&bytes).unwrap();
+
+            assert_eq!(blob.start, EPHEMERIS.start);
+            assert_eq!(blob.period, EPHEMERIS.period);
+            assert_eq!(blob.location, LOCATION);
+            assert_eq!(blob.shadow.len(), 4);
+            assert_eq!(blob.elevation.len(), 4);
+
+            let t0 = EPHEMERIS.start as i64;
+            for ts in (t0 - 600..t0 + 4 * 3600).step_by(600) {
+                assert_eq!(
+                    shadow_angle_from_unix_timestamp(&blob, ts),
+                    shadow_angle_from_unix_timestamp(&EPHEMERIS, ts)
+                );
+                assert_eq!(
+                    elevation_from_unix_timestamp(&blob, ts),
+                    elevation_from_unix_timestamp(&EPHEMERIS, ts)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_corrupted_blobs() {
+        let bytes = write_blob(&EPHEMERIS, LOCATION, false).unwrap();
+
+        assert_eq!(
+            EphemerisBlob::parse(&bytes[..HEADER_LEN]).err(),
+            Some(BlobError::TooShort)
+        );
+
+        let mut bad = bytes.clone();
+        bad[0] = b'N';
+        assert_eq!(EphemerisBlob::parse(&bad).err(), Some(BlobError::BadMagic));
+
+        let mut bad = bytes.clone();
+        bad[4] = 2;
+        assert_eq!(
+            EphemerisBlob::parse(&bad).err(),
+            Some(BlobError::UnsupportedVersion(2))
+        );
+
+        let mut bad = bytes.clone();
+        bad[HEADER_LEN] ^= 1;
+        assert_eq!(
+            EphemerisBlob::parse(&bad).err(),
+            Some(BlobError::BadChecksum)
+        );
+    }
+
+    #[test]
+    fn rejects_crafted_block_width() {
+        let mut bytes = write_blob(&EPHEMERIS, LOCATION, true).unwrap();
+        assert!(EphemerisBlob::parse(&bytes).is_ok());
+
+        // widen first shadow block past what can be read, checksum fixed up
+        let payload = read_u32(&bytes, 32 + 8) as usize;
+        bytes[payload + 4] = 32;
+        reseal(&mut bytes);
+
+        assert_eq!(
+            EphemerisBlob::parse(&bytes).err(),
+            Some(BlobError::BadPayload)
+        );
+    }
+
+    #[test]
+    fn rejects_crafted_sample_count() {
+        for compact in [false, true] {
+            let mut bytes = write_blob(&EPHEMERIS, LOCATION, compact).unwrap();
+            bytes[32 + 4..32 + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+            reseal(&mut bytes);
+
+            assert_eq!(
+                EphemerisBlob::parse(&bytes).err(),
+                Some(BlobError::BadPayload)
+            );
+        }
+    }
+
+    /// Recompute checksum of a tampered blob
+    fn reseal(bytes: &mut [u8]) {
+        let end = bytes.len() - CRC_LEN;
+        let crc = crc32(&bytes[..end]);
+        bytes[end..].copy_from_slice(&crc.to_le_bytes());
+    }
+
+    #[test]
+    fn bundled_table_roundtrip() {
+        let raw = write_blob(&MOON_EPHEMERIS, LOCATION, false).unwrap();
+        let compact = write_blob(&MOON_EPHEMERIS, LOCATION, true).unwrap();
+        println!(
+            "raw blob {} bytes, compact blob {} bytes",
+            raw.len(),
+            compact.len()
+        );
+
+        for bytes in [raw, compact] {
+            let blob = EphemerisBlob::parse(&bytes).unwrap();
+            for i in (0..MOON_EPHEMERIS.shadow.len()).step_by(97) {
+                assert_eq!(blob.shadow.sample(i), MOON_EPHEMERIS.shadow.sample(i));
+                assert_eq!(blob.elevation.sample(i), MOON_EPHEMERIS.elevation.sample(i));
+            }
+        }
+    }
+}
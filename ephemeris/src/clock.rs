@@ -0,0 +1,68 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, TimeZone};
+
+use crate::defs::Ephemeris;
+use crate::error::EphemerisError;
+use crate::shadow_angle_from_unix_timestamp;
+use crate::state::{moon_state_at, MoonState};
+use crate::units::Centidegrees;
+
+/// Anything that can be expressed as a unix timestamp in seconds
+pub trait UnixTime {
+    fn unix_timestamp(&self) -> i64;
+}
+
+impl UnixTime for SystemTime {
+    fn unix_timestamp(&self) -> i64 {
+        match self.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64,
+            // round towards the past like chrono does
+            Err(e) => {
+                let d = e.duration();
+                -(d.as_secs() as i64) - (d.subsec_nanos() > 0) as i64
+            }
+        }
+    }
+}
+
+impl<Tz: TimeZone> UnixTime for DateTime<Tz> {
+    fn unix_timestamp(&self) -> i64 {
+        self.timestamp()
+    }
+}
+
+/// Current unix timestamp given by the system clock
+pub fn unix_now() -> i64 {
+    SystemTime::now().unix_timestamp()
+}
+
+/// Return current moon shadow angle ranging [0,360[
+pub fn shadow_angle_now<E: Ephemeris + ?Sized>(data: &E) -> Result<Centidegrees, EphemerisError> {
+    shadow_angle_from_unix_timestamp(data, unix_now())
+}
+
+/// Return current moon state
+pub fn moon_state_now<E: Ephemeris + ?Sized>(data: &E) -> Result<MoonState, EphemerisError> {
+    moon_state_at(data, unix_now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+    use std::time::Duration;
+
+    #[test]
+    fn unix_time_conversions() {
+        let t = UNIX_EPOCH + Duration::from_secs(1_762_879_028);
+        assert_eq!(t.unix_timestamp(), 1_762_879_028);
+        let t = UNIX_EPOCH - Duration::from_millis(1500);
+        assert_eq!(t.unix_timestamp(), -2);
+
+        let utc = Utc.timestamp_opt(1_762_879_028, 0).unwrap();
+        assert_eq!(utc.unix_timestamp(), 1_762_879_028);
+        let paris = utc.with_timezone(&FixedOffset::east_opt(3600).unwrap());
+        assert_eq!(paris.unix_timestamp(), 1_762_879_028);
+    }
+}
@@ -0,0 +1,420 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::defs::{Channel, Ephemeris};
+
+/// Number of samples sharing a block header
+pub const BLOCK_LEN: usize = 128;
+
+/// Widest packed second difference a block may declare, in bits
+pub const MAX_WIDTH: u8 = 24;
+
+/// Shadow angles wrap around a full turn in decidegrees
+pub const SHADOW_MODULUS: u16 = 3600;
+
+/// Header of a block of `BLOCK_LEN` consecutive samples
+///
+/// Headers are stored as `Block::SIZE` little endian bytes so encoded channels
+/// can be borrowed straight from a byte buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Block {
+    /// first sample of the block in stored unit
+    pub first: i16,
+    /// difference between the second and first sample
+    pub delta: i16,
+    /// bit width of each packed second difference
+    pub width: u8,
+    /// bit offset of the block first packed second difference
+    pub offset: u32,
+}
+
+impl Block {
+    /// Size of a stored block header in bytes
+    pub const SIZE: usize = 9;
+
+    /// Read header of block `index` from stored headers
+    pub fn read(headers: &[u8], index: usize) -> Option<Block> {
+        let b = headers.get(index * Self::SIZE..(index + 1) * Self::SIZE)?;
+        Some(Block {
+            first: i16::from_le_bytes([b[0], b[1]]),
+            delta: i16::from_le_bytes([b[2], b[3]]),
+            width: b[4],
+            offset: u32::from_le_bytes([b[5], b[6], b[7], b[8]]),
+        })
+    }
+
+    /// Append stored header to provided buffer
+    #[cfg(feature = "alloc")]
+    pub fn write(&self, headers: &mut Vec<u8>) {
+        headers.extend_from_slice(&self.first.to_le_bytes());
+        headers.extend_from_slice(&self.delta.to_le_bytes());
+        headers.push(self.width);
+        headers.extend_from_slice(&self.offset.to_le_bytes());
+    }
+}
+
+/// Channel encoded as per block second differences
+///
+/// Each block stores its first sample and first difference in its header,
+/// every following sample is stored as the zigzag encoded difference between
+/// two consecutive differences, packed on the block bit width. Moon angles are
+/// smooth so most second differences fit in a few bits.
+///
+/// Encoding is lossless (modulo `modulus` for wrapping channels) and decoding a
+/// sample walks at most one block, so lookups stay O(1).
+#[derive(Debug, Clone, Copy)]
+pub struct CompactChannel<'a> {
+    /// number of encoded samples
+    pub len: u32,
+    /// samples wrap around this value when not zero
+    pub modulus: u16,
+    /// one stored `Block` header every `BLOCK_LEN` samples
+    pub blocks: &'a [u8],
+    /// packed second differences, least significant bit first
+    pub bits: &'a [u8],
+}
+
+impl Channel for CompactChannel<'_> {
+    fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    fn sample(&self, index: usize) -> Option<i32> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let block = Block::read(self.blocks, index / BLOCK_LEN)?;
+        if block.width > MAX_WIDTH {
+            return None;
+        }
+        let mut value = block.first as i32;
+        let mut delta = block.delta as i32;
+
+        // rebuild every sample from the start of the block, wrapping so
+        // crafted data yields garbage rather than a panic
+        for i in 1..=(index % BLOCK_LEN) {
+            if i >= 2 {
+                let pos = block.offset as usize + (i - 2) * block.width as usize;
+                delta = delta.wrapping_add(unzigzag(read_bits(self.bits, pos, block.width)?));
+            }
+            value = value.wrapping_add(delta);
+        }
+
+        if self.modulus != 0 {
+            value = value.rem_euclid(self.modulus as i32);
+        }
+
+        Some(value)
+    }
+}
+
+/// Ephemeris whose channels are stored as `CompactChannel`
+#[derive(Debug, Clone, Copy)]
+pub struct CompactEphemeris<'a> {
+    // starting unix timestamp
+    pub start: u64,
+    // time between each entry in seconds
+    pub period: u32,
+    // moon shadow angles in decidegrees
+    pub shadow: CompactChannel<'a>,
+    // moon elevation angle in degrees
+    pub elevation: CompactChannel<'a>,
+}
+
+impl<'a> Ephemeris for CompactEphemeris<'a> {
+    type Shadow = CompactChannel<'a>;
+    type Elevation = CompactChannel<'a>;
+
+    fn start(&self) -> u64 {
+        self.start
+    }
+
+    fn period(&self) -> u32 {
+        self.period
+    }
+
+    fn shadow(&self) -> &CompactChannel<'a> {
+        &self.shadow
+    }
+
+    fn elevation(&self) -> &CompactChannel<'a> {
+        &self.elevation
+    }
+}
+
+/// Owned storage of an encoded channel
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactChannelBuf {
+    len: u32,
+    modulus: u16,
+    blocks: Vec<u8>,
+    bits: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl CompactChannelBuf {
+    /// Encode provided samples, samples wrap around `modulus` when not zero
+    ///
+    /// Return None if a sample or a first difference does not fit an i16, a
+    /// difference overflows an i32 or a second difference needs more than
+    /// `MAX_WIDTH` bits, decoding would fail on such data.
+    pub fn encode<C: Channel + ?Sized>(samples: &C, modulus: u16) -> Option<Self> {
+        let len = u32::try_from(samples.len()).ok()?;
+
+        // get [-modulus/2;modulus/2[ equivalent of a difference
+        let wrap = |d: i32| match modulus {
+            0 => d,
+            m => {
+                let m = m as i32;
+                (d.rem_euclid(m) + m / 2).rem_euclid(m) - m / 2
+            }
+        };
+
+        let mut blocks = Vec::new();
+        let mut bits = BitWriter::default();
+
+        let mut block_start = 0;
+        while block_start < samples.len() {
+            let block_end = samples.len().min(block_start + BLOCK_LEN);
+            let values = (block_start..block_end)
+                .map(|i| samples.sample(i))
+                .collect::<Option<Vec<_>>>()?;
+
+            // wrapped differences between consecutive samples
+            let deltas = values
+                .windows(2)
+                .map(|w| w[1].checked_sub(w[0]).map(wrap))
+                .collect::<Option<Vec<i32>>>()?;
+            // zigzag encoded second differences
+            let second = deltas
+                .windows(2)
+                .map(|w| w[1].checked_sub(w[0]).map(zigzag))
+                .collect::<Option<Vec<u32>>>()?;
+            let width = second
+                .iter()
+                .map(|&z| u32::BITS - z.leading_zeros())
+                .max()
+                .unwrap_or(0) as u8;
+            if width > MAX_WIDTH {
+                return None;
+            }
+
+            Block {
+                first: i16::try_from(values[0]).ok()?,
+                delta: i16::try_from(deltas.first().copied().unwrap_or(0)).ok()?,
+                width,
+                offset: u32::try_from(bits.len).ok()?,
+            }
+            .write(&mut blocks);
+            for z in second {
+                bits.push(z, width);
+            }
+
+            block_start = block_end;
+        }
+
+        Some(CompactChannelBuf {
+            len,
+            modulus,
+            blocks,
+            bits: bits.bytes,
+        })
+    }
+
+    /// Borrow encoded data as a channel lookups can use
+    pub fn as_channel(&self) -> CompactChannel<'_> {
+        CompactChannel {
+            len: self.len,
+            modulus: self.modulus,
+            blocks: &self.blocks,
+            bits: &self.bits,
+        }
+    }
+
+    /// Encoded size in bytes
+    pub fn size(&self) -> usize {
+        self.blocks.len() + self.bits.len()
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    // number of bits written
+    len: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl BitWriter {
+    fn push(&mut self, value: u32, width: u8) {
+        for i in 0..width {
+            if self.len & 7 == 0 {
+                self.bytes.push(0);
+            }
+            if (value >> i) & 1 != 0 {
+                self.bytes[self.len / 8] |= 1 << (self.len % 8);
+            }
+            self.len += 1;
+        }
+    }
+}
+
+/// Read `width` bits (at most `MAX_WIDTH`) starting at bit `pos`
+fn read_bits(bits: &[u8], pos: usize, width: u8) -> Option<u32> {
+    if width == 0 {
+        return Some(0);
+    }
+    if width > MAX_WIDTH {
+        return None;
+    }
+
+    // gather a 32 bits window holding the whole value
+    let bytes = bits.get(pos / 8..)?;
+    let window = bytes
+        .iter()
+        .take(4)
+        .enumerate()
+        .fold(0u32, |w, (i, &b)| w | (b as u32) << (8 * i));
+
+    Some((window >> (pos % 8)) & ((1 << width) - 1))
+}
+
+/// Map signed values to unsigned ones so small magnitudes use few bits
+#[cfg(feature = "alloc")]
+fn zigzag(v: i32) -> u32 {
+    ((v << 1) ^ (v >> 31)) as u32
+}
+
+fn unzigzag(z: u32) -> i32 {
+    ((z >> 1) as i32) ^ -((z & 1) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{elevation_from_unix_timestamp, shadow_angle_from_unix_timestamp, MOON_EPHEMERIS};
+
+    #[test]
+    fn zigzag_roundtrip() {
+        for v in [0, 1, -1, 2, -2, 1800, -1800, 7199, -7200] {
+            assert_eq!(unzigzag(zigzag(v)), v);
+        }
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+    }
+
+    #[test]
+    fn wrapping_channel() {
+        let samples: &[u16] = &[3590, 3595, 0, 5, 10, 3, 3599, 0];
+        let buf = CompactChannelBuf::encode(samples, SHADOW_MODULUS).unwrap();
+        let channel = buf.as_channel();
+
+        assert_eq!(channel.len(), samples.len());
+        for (i, &s) in samples.iter().enumerate() {
+            assert_eq!(channel.sample(i), Some(s as i32));
+        }
+        assert_eq!(channel.sample(samples.len()), None);
+    }
+
+    #[test]
+    fn crafted_blocks_do_not_panic() {
+        let mut blocks = std::vec::Vec::new();
+        // huge first difference overflows i32 within a few samples
+        Block {
+            first: i16::MAX,
+            delta: i16::MAX,
+            width: 24,
+            offset: 0,
+        }
+        .write(&mut blocks);
+        let bits = [0xfe; 400];
+        let sample = |blocks: &[u8], index| {
+            let channel = CompactChannel {
+                len: 100,
+                modulus: 0,
+                blocks,
+                bits: &bits,
+            };
+            channel.sample(index)
+        };
+        for i in 0..100 {
+            assert!(sample(&blocks, i).is_some());
+        }
+
+        // too wide to be read
+        blocks[4] = 40;
+        assert_eq!(sample(&blocks, 0), None);
+        assert_eq!(sample(&blocks, 5), None);
+    }
+
+    #[test]
+    fn undecodable_samples_are_refused() {
+        // second difference needs 26 bits
+        let samples: &[i32] = &[0, 0, 20_000_000];
+        assert_eq!(CompactChannelBuf::encode(samples, 0), None);
+
+        // differences overflow an i32
+        let samples: &[i32] = &[0, i32::MAX, -5];
+        assert_eq!(CompactChannelBuf::encode(samples, 0), None);
+        let samples: &[i32] = &[0, 30_000, i32::MIN];
+        assert_eq!(CompactChannelBuf::encode(samples, 0), None);
+
+        // widest second difference still roundtrips
+        let samples: &[i32] = &[0, 0, 4_000_000];
+        let buf = CompactChannelBuf::encode(samples, 0).unwrap();
+        assert_eq!(buf.as_channel().sample(2), Some(4_000_000));
+    }
+
+    #[test]
+    fn empty_channel() {
+        let samples: &[i8] = &[];
+        let buf = CompactChannelBuf::encode(samples, 0).unwrap();
+        assert!(buf.as_channel().is_empty());
+        assert_eq!(buf.as_channel().sample(0), None);
+    }
+
+    #[test]
+    fn equivalence_with_raw_table() {
+        // tolerance: encoding is lossless, every lookup must match exactly
+        let shadow = CompactChannelBuf::encode(MOON_EPHEMERIS.shadow, SHADOW_MODULUS).unwrap();
+        let elevation = CompactChannelBuf::encode(MOON_EPHEMERIS.elevation, 0).unwrap();
+        let compact = CompactEphemeris {
+            start: MOON_EPHEMERIS.start,
+            period: MOON_EPHEMERIS.period,
+            shadow: shadow.as_channel(),
+            elevation: elevation.as_channel(),
+        };
+
+        let raw_size = core::mem::size_of_val(MOON_EPHEMERIS.shadow)
+            + core::mem::size_of_val(MOON_EPHEMERIS.elevation);
+        let compact_size = shadow.size() + elevation.size();
+        println!("raw table {raw_size} bytes, compact table {compact_size} bytes");
+        assert!(2 * compact_size < raw_size);
+
+        for (i, (&s, &e)) in MOON_EPHEMERIS
+            .shadow
+            .iter()
+            .zip(MOON_EPHEMERIS.elevation)
+            .enumerate()
+        {
+            assert_eq!(compact.shadow.sample(i), Some(s as i32));
+            assert_eq!(compact.elevation.sample(i), Some(e as i32));
+        }
+
+        let t0 = MOON_EPHEMERIS.start as i64;
+        let p = MOON_EPHEMERIS.period as i64;
+        for ts in (t0 - p..t0 + p * (MOON_EPHEMERIS.shadow.len() as i64 + 1)).step_by(4321) {
+            assert_eq!(
+                shadow_angle_from_unix_timestamp(&compact, ts),
+                shadow_angle_from_unix_timestamp(&MOON_EPHEMERIS, ts)
+            );
+            assert_eq!(
+                elevation_from_unix_timestamp(&compact, ts),
+                elevation_from_unix_timestamp(&MOON_EPHEMERIS, ts)
+            );
+        }
+    }
+}
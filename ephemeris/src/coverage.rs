@@ -0,0 +1,106 @@
+use crate::defs::{Channel, Ephemeris};
+
+/// Timestamps a channel holds samples for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelCoverage {
+    // number of stored samples
+    pub len: usize,
+    // timestamp of the first sample
+    pub first: i64,
+    // timestamp of the last sample, latest one lookups succeed at
+    pub last: i64,
+}
+
+impl ChannelCoverage {
+    /// Coverage of provided channel, None if it is empty
+    pub fn of<E: Ephemeris + ?Sized, C: Channel + ?Sized>(data: &E, channel: &C) -> Option<Self> {
+        let len = channel.len();
+        let first = data.start() as i64;
+        let span = (data.period() as i64).checked_mul(len.checked_sub(1)? as i64)?;
+        Some(ChannelCoverage {
+            len,
+            first,
+            last: first.checked_add(span)?,
+        })
+    }
+
+    /// Check whether lookups succeed at provided timestamp
+    pub fn contains(&self, unix: i64) -> bool {
+        self.first <= unix && unix <= self.last
+    }
+}
+
+/// Coverage of every channel of an ephemeris
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Coverage {
+    pub shadow: Option<ChannelCoverage>,
+    pub elevation: Option<ChannelCoverage>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MoonEphemeris;
+
+    const EPHEMERIS: MoonEphemeris = MoonEphemeris {
+        start: 1000,
+        period: 3600,
+        shadow: &[0, 9000, 18000, 27000],
+        elevation: &[0, 90, 0],
+    };
+
+    #[test]
+    fn channels_may_differ() {
+        let coverage = EPHEMERIS.coverage();
+        assert_eq!(
+            coverage.shadow,
+            Some(ChannelCoverage {
+                len: 4,
+                first: 1000,
+                last: 1000 + 3 * 3600
+            })
+        );
+        assert_eq!(coverage.elevation.map(|c| c.last), Some(1000 + 2 * 3600));
+
+        // ephemeris ends with its shortest channel
+        assert_eq!(EPHEMERIS.end(), Some(1000 + 2 * 3600));
+        assert!(!EPHEMERIS.contains(999));
+        assert!(EPHEMERIS.contains(1000));
+        assert!(EPHEMERIS.contains(1000 + 2 * 3600));
+        assert!(!EPHEMERIS.contains(1001 + 2 * 3600));
+
+        assert_eq!(EPHEMERIS.remaining(1000), 2 * 3600);
+        assert_eq!(EPHEMERIS.remaining(0), 1000 + 2 * 3600);
+        assert_eq!(EPHEMERIS.remaining(1000 + 5 * 3600), 0);
+    }
+
+    #[test]
+    fn empty_channels_are_left_out() {
+        let shadow_only = MoonEphemeris {
+            elevation: &[],
+            ..EPHEMERIS
+        };
+        assert_eq!(shadow_only.coverage().elevation, None);
+        // shadow only tables end with their shadow channel
+        assert_eq!(shadow_only.end(), Some(1000 + 3 * 3600));
+        assert!(shadow_only.contains(1000 + 3 * 3600));
+        assert_eq!(shadow_only.remaining(1000), 3 * 3600);
+
+        let empty = MoonEphemeris {
+            shadow: &[],
+            ..shadow_only
+        };
+        assert_eq!(empty.end(), None);
+        assert!(!empty.contains(1000));
+        assert_eq!(empty.remaining(1000), 0);
+    }
+
+    #[test]
+    fn bundled_table_lasts_years() {
+        let data = &crate::MOON_EPHEMERIS;
+        let end = data.end().unwrap();
+        assert_eq!(data.coverage().shadow.unwrap().last, end);
+        // bundled table spans ten years
+        assert!(end - data.start as i64 > 9 * 365 * 86400);
+    }
+}
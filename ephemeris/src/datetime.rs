@@ -0,0 +1,40 @@
+//! Conversions between Julian days and chrono dates
+//!
+//! Kept apart from `time` as the build script includes that module without
+//! chrono at hand.
+
+use chrono::{DateTime, Utc};
+
+use crate::time::{JulianDay, UNIX_EPOCH_JD};
+
+impl From<DateTime<Utc>> for JulianDay {
+    fn from(t: DateTime<Utc>) -> Self {
+        let seconds = t.timestamp() as f64 + t.timestamp_subsec_nanos() as f64 / 1e9;
+        JulianDay(UNIX_EPOCH_JD + seconds / 86400.0)
+    }
+}
+
+impl From<JulianDay> for DateTime<Utc> {
+    /// Saturates to the range chrono can represent
+    fn from(jd: JulianDay) -> Self {
+        let ms = libm::round((jd.0 - UNIX_EPOCH_JD) * 86_400_000.0) as i64;
+        DateTime::from_timestamp_millis(ms).unwrap_or(match ms < 0 {
+            true => DateTime::<Utc>::MIN_UTC,
+            false => DateTime::<Utc>::MAX_UTC,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::J2000_JD;
+    use chrono::TimeZone;
+
+    #[test]
+    fn chrono_conversions() {
+        let t = Utc.with_ymd_and_hms(2000, 1, 1, 12, 0, 0).unwrap();
+        assert_eq!(JulianDay::from(t), JulianDay(J2000_JD));
+        assert_eq!(DateTime::<Utc>::from(JulianDay(J2000_JD)), t);
+    }
+}
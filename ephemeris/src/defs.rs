@@ -1,3 +1,8 @@
+use core::ops::Range;
+
+use crate::coverage::{ChannelCoverage, Coverage};
+use crate::samples::{Entries, Samples};
+
 pub struct MoonEphemeris {
     // starting unix timestamp
     pub start: u64,
@@ -8,3 +13,123 @@ pub struct MoonEphemeris {
     // moon elevation angle in degrees
     pub elevation: &'static [i8],
 }
+
+/// A sequence of samples taken every ephemeris period
+pub trait Channel {
+    /// Number of stored samples
+    fn len(&self) -> usize;
+
+    /// Sample at provided index in stored unit
+    fn sample(&self, index: usize) -> Option<i32>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Into<i32> + Copy> Channel for [T] {
+    fn len(&self) -> usize {
+        <[T]>::len(self)
+    }
+
+    fn sample(&self, index: usize) -> Option<i32> {
+        self.get(index).map(|&v| v.into())
+    }
+}
+
+/// Any representation of an ephemeris lookups can be performed on
+pub trait Ephemeris {
+    type Shadow: Channel + ?Sized;
+    type Elevation: Channel + ?Sized;
+
+    /// Starting unix timestamp
+    fn start(&self) -> u64;
+
+    /// Time between each entry in seconds
+    fn period(&self) -> u32;
+
+    /// Moon shadow angles in decidegrees
+    fn shadow(&self) -> &Self::Shadow;
+
+    /// Moon elevation angles in degrees
+    fn elevation(&self) -> &Self::Elevation;
+
+    /// Timestamps every channel holds samples for
+    fn coverage(&self) -> Coverage {
+        Coverage {
+            shadow: ChannelCoverage::of(self, self.shadow()),
+            elevation: ChannelCoverage::of(self, self.elevation()),
+        }
+    }
+
+    /// Last timestamp every stored channel can be looked up at, empty
+    /// channels are left out so a shadow only table still has an end
+    ///
+    /// Return None if every channel is empty.
+    fn end(&self) -> Option<i64> {
+        let coverage = self.coverage();
+        [coverage.shadow, coverage.elevation]
+            .into_iter()
+            .flatten()
+            .map(|c| c.last)
+            .min()
+    }
+
+    /// Check whether every stored channel can be looked up at provided
+    /// timestamp
+    fn contains(&self, unix: i64) -> bool {
+        match self.end() {
+            Some(end) => self.start() as i64 <= unix && unix <= end,
+            None => false,
+        }
+    }
+
+    /// Seconds left from provided timestamp until the ephemeris ends, zero
+    /// once it has expired
+    ///
+    /// Time before the ephemeris start counts as remaining, use `contains`
+    /// to know whether lookups succeed now.
+    fn remaining(&self, unix: i64) -> u64 {
+        match self.end() {
+            Some(end) => end.saturating_sub(unix).max(0) as u64,
+            None => 0,
+        }
+    }
+
+    /// Iterate over interpolated values every `step` seconds in provided range
+    ///
+    /// Yields timestamp, shadow angle and elevation. Iteration stops after the
+    /// first failed lookup, a zero step yields nothing.
+    fn samples(&self, range: Range<i64>, step: u32) -> Samples<'_, Self> {
+        Samples::new(self, range, step)
+    }
+
+    /// Iterate over stored entries
+    ///
+    /// Yields timestamp, shadow angle and elevation as stored for every index
+    /// both channels hold a sample for.
+    fn entries(&self) -> Entries<'_, Self> {
+        Entries::new(self)
+    }
+}
+
+impl Ephemeris for MoonEphemeris {
+    type Shadow = [u16];
+    type Elevation = [i8];
+
+    fn start(&self) -> u64 {
+        self.start
+    }
+
+    fn period(&self) -> u32 {
+        self.period
+    }
+
+    fn shadow(&self) -> &[u16] {
+        self.shadow
+    }
+
+    fn elevation(&self) -> &[i8] {
+        self.elevation
+    }
+}
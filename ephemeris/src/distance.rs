@@ -0,0 +1,151 @@
+use libm::{asin, round};
+
+use crate::lunar::moon_position;
+use crate::rise::MOON_RADIUS_KM;
+use crate::units::Centidegrees;
+
+/// Step used to bracket apsides in seconds
+const BRACKET_STEP_S: i64 = 6 * 3600;
+/// Apsides are at most this far apart, in seconds
+const APSIS_WINDOW_S: i64 = 17 * 24 * 3600;
+/// Fraction of the perigee to apogee range a supermoon lies within
+const SUPERMOON_FRACTION: f64 = 0.1;
+
+/// Closest and farthest points of the lunar orbit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Apsis {
+    Perigee,
+    Apogee,
+}
+
+/// Moon center distance from Earth center in kilometers
+pub fn distance_km_at(unix: i64) -> f64 {
+    moon_position(unix).distance
+}
+
+/// Moon apparent diameter seen from Earth center
+///
+/// Diameter seen from the Earth surface is up to 2% larger when the
+/// moon is high in the sky.
+pub fn angular_diameter_at(unix: i64) -> Centidegrees {
+    let degrees = 2.0 * asin(MOON_RADIUS_KM / distance_km_at(unix)).to_degrees();
+    Centidegrees(round(100.0 * degrees) as i32)
+}
+
+/// Return the first apsis reached after provided timestamp with its time
+pub fn next_apsis(after: i64) -> (Apsis, i64) {
+    // distance slope over a minute, changes sign at apsides
+    const H: i64 = 30;
+    let slope = |t: i64| distance_km_at(t + H) - distance_km_at(t - H);
+
+    let mut t0 = after;
+    let mut s0 = slope(t0);
+    loop {
+        let t1 = t0 + BRACKET_STEP_S;
+        let s1 = slope(t1);
+
+        if (s0 < 0.0) != (s1 < 0.0) {
+            // bisect bracket down to the second
+            let (mut lo, mut hi) = (t0, t1);
+            while hi - lo > 1 {
+                let mid = lo + (hi - lo) / 2;
+                if (slope(mid) < 0.0) == (s0 < 0.0) {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+            let apsis = match s0 < 0.0 {
+                true => Apsis::Perigee,
+                false => Apsis::Apogee,
+            };
+            return (apsis, hi);
+        }
+
+        (t0, s0) = (t1, s1);
+    }
+}
+
+/// Return time of the first perigee after provided timestamp
+pub fn next_perigee(after: i64) -> i64 {
+    match next_apsis(after) {
+        (Apsis::Perigee, t) => t,
+        (Apsis::Apogee, t) => next_apsis(t).1,
+    }
+}
+
+/// Return time of the first apogee after provided timestamp
+pub fn next_apogee(after: i64) -> i64 {
+    match next_apsis(after) {
+        (Apsis::Apogee, t) => t,
+        (Apsis::Perigee, t) => next_apsis(t).1,
+    }
+}
+
+/// Tell if the moon is a supermoon at provided timestamp, meant for full
+/// moon times as given by `phase_events`
+///
+/// Follows the usual definition of a full moon lying within 10% of the
+/// perigee end of its orbit, the orbit being bounded by the apsides right
+/// before and after the provided timestamp.
+pub fn is_supermoon(unix: i64) -> bool {
+    // last apsis before the timestamp
+    let mut before = next_apsis(unix - APSIS_WINDOW_S);
+    loop {
+        let next = next_apsis(before.1);
+        if next.1 > unix {
+            break;
+        }
+        before = next;
+    }
+    let after = next_apsis(unix);
+
+    let (perigee, apogee) = match before.0 {
+        Apsis::Perigee => (before.1, after.1),
+        Apsis::Apogee => (after.1, before.1),
+    };
+    let (perigee, apogee) = (distance_km_at(perigee), distance_km_at(apogee));
+    distance_km_at(unix) <= perigee + SUPERMOON_FRACTION * (apogee - perigee)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_bounds() {
+        let start = 1_762_879_028;
+        for day in 0..60 {
+            let t = start + day * 86400;
+            let d = distance_km_at(t);
+            assert!(d > 356_000.0 && d < 407_000.0);
+            // the moon spans about half a degree
+            let a = angular_diameter_at(t);
+            assert!(a >= Centidegrees(48) && a <= Centidegrees(57));
+        }
+    }
+
+    #[test]
+    fn published_apsides() {
+        // 2025-11-05 22:27 UTC, closest perigee of the year at 356833 km
+        let perigee = next_perigee(1_762_128_000);
+        assert!((perigee - 1_762_381_620).abs() < 3600, "{perigee}");
+        assert!((distance_km_at(perigee) - 356_833.0).abs() < 20.0);
+
+        // apsides alternate about two weeks apart
+        let (apsis, apogee) = next_apsis(perigee);
+        assert_eq!(apsis, Apsis::Apogee);
+        assert_eq!(next_apogee(perigee), apogee);
+        assert!(apogee - perigee > 12 * 86400 && apogee - perigee < 17 * 86400);
+        assert!(distance_km_at(apogee) > 404_000.0);
+    }
+
+    #[test]
+    fn supermoons() {
+        // full moons of 2025-11-05 13:19 and 2025-12-04 23:14 are supermoons
+        assert!(is_supermoon(1_762_348_740));
+        assert!(is_supermoon(1_764_890_040));
+        // full moon of 2025-04-13 00:22 lies near apogee
+        assert!(!is_supermoon(1_744_503_720));
+    }
+}
@@ -0,0 +1,31 @@
+use core::fmt;
+
+/// Reasons an ephemeris lookup can fail
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EphemerisError {
+    /// requested timestamp predates the first entry of the ephemeris
+    BeforeStart,
+    /// requested timestamp is past the last entry of the ephemeris,
+    /// `last_valid` is the latest unix timestamp that can still be looked up
+    AfterEnd { last_valid: i64 },
+    /// ephemeris holds no entry for the requested channel
+    EmptyTable,
+    /// ephemeris period is zero
+    InvalidPeriod,
+}
+
+impl fmt::Display for EphemerisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EphemerisError::BeforeStart => write!(f, "timestamp predates ephemeris"),
+            EphemerisError::AfterEnd { last_valid } => {
+                write!(f, "ephemeris expired (last valid timestamp {last_valid})")
+            }
+            EphemerisError::EmptyTable => write!(f, "ephemeris table is empty"),
+            EphemerisError::InvalidPeriod => write!(f, "ephemeris period is zero"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EphemerisError {}
@@ -0,0 +1,269 @@
+//! Bundled table generation, shared by the build script and the tests
+//!
+//! `src/data.rs` is the output for `ephemeris.conf`, rewrite it with
+//! `UPDATE_BUNDLED_TABLE=1 cargo test bundled_table_matches_config`.
+//!
+//! Configuration is a plain text file, not TOML: one `key = value` pair per
+//! line, `#` starts a comment running to the end of the line and blank lines
+//! are ignored. Values are bare numbers, every key is required but the
+//! observer ones, which default to zero:
+//!
+//! | key       | value                                         |
+//! |-----------|-----------------------------------------------|
+//! | epoch     | unix timestamp of the first entry             |
+//! | span      | table span in days, positive                  |
+//! | period    | time between each entry in seconds, positive  |
+//! | latitude  | observer latitude in degrees [-90;90]         |
+//! | longitude | observer longitude in degrees [-180;180]      |
+//! | height    | observer height above sea level in metres     |
+
+use std::fmt::Write;
+
+use crate::lunar::{analytic_shadow_angle_from_unix_timestamp, moon_position};
+use crate::observer::Observer;
+use crate::rules::{validate_tables, Violation};
+
+/// Table generation parameters
+pub struct Config {
+    // unix timestamp of the first entry
+    pub epoch: u64,
+    // table span in days
+    pub span: u64,
+    // time between each entry in seconds
+    pub period: u32,
+    // location moon elevation is computed for
+    pub observer: Observer,
+}
+
+impl Config {
+    /// Parse configuration text, see module documentation
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut epoch = None;
+        let mut span = None;
+        let mut period = None;
+        let mut observer = Observer::default();
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| format!("line {}: expected `key = value`", n + 1))?;
+
+            let invalid = |e: &dyn std::fmt::Display| format!("line {}: {key}: {e}", n + 1);
+            match key {
+                "epoch" => epoch = Some(value.parse().map_err(|e| invalid(&e))?),
+                "span" => span = Some(value.parse().map_err(|e| invalid(&e))?),
+                "period" => period = Some(value.parse().map_err(|e| invalid(&e))?),
+                "latitude" => observer.latitude = value.parse().map_err(|e| invalid(&e))?,
+                "longitude" => observer.longitude = value.parse().map_err(|e| invalid(&e))?,
+                "height" => observer.height = value.parse().map_err(|e| invalid(&e))?,
+                _ => return Err(format!("line {}: unknown key `{key}`", n + 1)),
+            }
+        }
+
+        let config = Config {
+            epoch: epoch.ok_or("missing epoch")?,
+            span: span.ok_or("missing span")?,
+            period: period.ok_or("missing period")?,
+            observer,
+        };
+        config.check()?;
+        Ok(config)
+    }
+
+    /// Refuse values no sensible table can be computed for
+    fn check(&self) -> Result<(), String> {
+        if self.span == 0 {
+            return Err("span must be positive".into());
+        }
+        if self.period == 0 {
+            return Err("period must be positive".into());
+        }
+        if self
+            .span
+            .checked_mul(86400)
+            .map_or(true, |s| s < self.period as u64)
+        {
+            return Err("span must hold at least one period".into());
+        }
+        let end = self.span * 86400;
+        if self
+            .epoch
+            .checked_add(end)
+            .map_or(true, |e| e > i64::MAX as u64)
+        {
+            return Err("table end does not fit a unix timestamp".into());
+        }
+
+        let Observer {
+            latitude,
+            longitude,
+            height,
+        } = self.observer;
+        if !(-90.0..=90.0).contains(&latitude) {
+            return Err(format!("latitude {latitude} out of [-90;90]"));
+        }
+        if !(-180.0..=180.0).contains(&longitude) {
+            return Err(format!("longitude {longitude} out of [-180;180]"));
+        }
+        if !height.is_finite() {
+            return Err(format!("height {height} is not a number"));
+        }
+        Ok(())
+    }
+}
+
+/// Compute table samples, refuse tables breaking a sanity rule
+pub fn compute(config: &Config) -> Result<(Vec<u16>, Vec<i8>), Violation> {
+    let count = config.span * 86400 / config.period as u64;
+    let timestamps = (0..count).map(|i| (config.epoch + i * config.period as u64) as i64);
+
+    let mut shadow = Vec::new();
+    let mut elevation = Vec::new();
+    for unix in timestamps {
+        // shadow angle is stored in decidegrees
+        shadow.push((analytic_shadow_angle_from_unix_timestamp(unix).0 / 10) as u16);
+
+        // elevation is stored in whole degrees truncated towards zero
+        let (_, h) = config
+            .observer
+            .horizontal_degrees(&moon_position(unix), unix);
+        elevation.push(h as i8);
+    }
+
+    match validate_tables(config.epoch, config.period, &shadow, &elevation) {
+        Some(violation) => Err(violation),
+        None => Ok((shadow, elevation)),
+    }
+}
+
+/// Render table as the source of a `MOON_EPHEMERIS` constant
+pub fn render(config: &Config, shadow: &[u16], elevation: &[i8]) -> String {
+    let mut out = String::new();
+    writeln!(out, "use crate::defs::MoonEphemeris;").unwrap();
+    writeln!(
+        out,
+        "pub const MOON_EPHEMERIS: MoonEphemeris = MoonEphemeris {{"
+    )
+    .unwrap();
+    writeln!(out, "    start: {},", config.epoch).unwrap();
+    writeln!(out, "    period: {},", config.period).unwrap();
+    writeln!(out, "    shadow: &[").unwrap();
+    for a in shadow {
+        writeln!(out, "        {a},").unwrap();
+    }
+    writeln!(out, "    ],").unwrap();
+    writeln!(out, "    elevation: &[").unwrap();
+    for e in elevation {
+        writeln!(out, "        {e},").unwrap();
+    }
+    writeln!(out, "    ],").unwrap();
+    writeln!(out, "}};").unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = include_str!("../ephemeris.conf");
+
+    /// Repository configuration cut down to a few weeks
+    fn short_config() -> Config {
+        Config {
+            span: 30,
+            ..Config::parse(CONFIG).unwrap()
+        }
+    }
+
+    #[test]
+    fn repository_config_parses() {
+        let config = Config::parse(CONFIG).unwrap();
+        assert_eq!(config.period, 3600);
+        assert_eq!(config.span, 3650);
+        assert_eq!(config.observer.latitude, 44.85);
+        assert_eq!(config.observer.longitude, -0.55);
+    }
+
+    #[test]
+    fn generation_is_reproducible() {
+        let config = short_config();
+        let render_once = || {
+            let (shadow, elevation) = compute(&config).unwrap();
+            render(&config, &shadow, &elevation)
+        };
+        let first = render_once();
+        assert_eq!(first.as_bytes(), render_once().as_bytes());
+        assert!(first.starts_with("use crate::defs::MoonEphemeris;\n"));
+        assert_eq!(first.lines().count(), 2 * 30 * 24 + 9);
+    }
+
+    #[test]
+    fn bundled_table_matches_config() {
+        let config = Config::parse(CONFIG).unwrap();
+        let (shadow, elevation) = compute(&config).unwrap();
+        let table = render(&config, &shadow, &elevation);
+
+        // UPDATE_BUNDLED_TABLE=1 rewrites the table after a config change
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/data.rs");
+        if std::env::var_os("UPDATE_BUNDLED_TABLE").is_some() {
+            std::fs::write(path, &table).unwrap();
+        }
+        let bundled = std::fs::read_to_string(path).unwrap();
+        assert!(
+            bundled == table,
+            "src/data.rs is out of date, see ephemeris.conf"
+        );
+    }
+
+    #[test]
+    fn malformed_configs_are_refused() {
+        let base = "epoch = 1762879028\nspan = 10\nperiod = 3600\n";
+        assert!(Config::parse(base).is_ok());
+
+        for (text, error) in [
+            ("span = 10\nperiod = 3600", "missing epoch"),
+            ("epoch = 0\nperiod = 3600", "missing span"),
+            ("epoch = 0\nspan = 10", "missing period"),
+            (
+                "epoch = 0\nspan = 10\nperiod",
+                "line 3: expected `key = value`",
+            ),
+            ("epoch = soon\nspan = 10\nperiod = 3600", "line 1: epoch:"),
+            ("epoch = -1\nspan = 10\nperiod = 3600", "line 1: epoch:"),
+            (
+                "epoch = 0\nspan = 10\nperiod = 3600\ncolor = 3",
+                "line 4: unknown key `color`",
+            ),
+            // TOML syntax is not understood
+            ("epoch = 0\nspan = 10\nperiod = 3600\n[site]", "line 4:"),
+            ("epoch = \"0\"\nspan = 10\nperiod = 3600", "line 1: epoch:"),
+        ] {
+            let e = Config::parse(text).err().unwrap();
+            assert!(e.starts_with(error), "{text:?}: {e}");
+        }
+    }
+
+    #[test]
+    fn out_of_range_values_are_refused() {
+        let base = "epoch = 1762879028\nspan = 10\nperiod = 3600\n";
+        for (extra, error) in [
+            ("span = 0", "span must be positive"),
+            ("period = 0", "period must be positive"),
+            ("period = 4000000000", "span must hold at least one period"),
+            ("epoch = 18446744073709551615", "table end does not fit"),
+            ("latitude = 90.5", "latitude 90.5 out of [-90;90]"),
+            ("latitude = NaN", "latitude NaN out of [-90;90]"),
+            ("longitude = -181", "longitude -181 out of [-180;180]"),
+            ("height = inf", "height inf is not a number"),
+        ] {
+            let text = format!("{base}{extra}\n");
+            let e = Config::parse(&text).err().unwrap();
+            assert!(e.starts_with(error), "{extra}: {e}");
+        }
+    }
+}
@@ -0,0 +1,86 @@
+/// Interpolation scheme used between two consecutive ephemeris entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    /// straight line between the two surrounding entries
+    #[default]
+    Linear,
+    /// cubic Hermite spline using Catmull-Rom tangents
+    CubicHermite,
+    /// 4-point Lagrange polynomial
+    Lagrange4,
+}
+
+impl Interpolation {
+    /// Interpolate between p[1] and p[2] after `elapsed` seconds out of `period`
+    ///
+    /// p[0] and p[3] are the entries surrounding the interval, they are only
+    /// used by cubic schemes. Every point must already be unwrapped around p[1].
+    pub(crate) fn interpolate(self, p: [i32; 4], elapsed: i32, period: i32) -> i32 {
+        let [p0, p1, p2, p3] = p.map(|v| v as i128);
+        let e = elapsed as i128;
+        let t = period as i128;
+
+        let value = match self {
+            Interpolation::Linear => {
+                return p[1] + elapsed * (p[2] - p[1]) / period;
+            }
+            Interpolation::CubicHermite => {
+                // Catmull-Rom polynomial evaluated with x = e/t, scaled by 2t^3
+                let n = e * t * t * (p2 - p0)
+                    + e * e * t * (2 * p0 - 5 * p1 + 4 * p2 - p3)
+                    + e * e * e * (-p0 + 3 * p1 - 3 * p2 + p3);
+                p1 + n / (2 * t * t * t)
+            }
+            Interpolation::Lagrange4 => {
+                // Lagrange basis on nodes -t, 0, t, 2t, scaled by 6t^3
+                let n = -p0 * e * (e - t) * (e - 2 * t) + 3 * p1 * (e + t) * (e - t) * (e - 2 * t)
+                    - 3 * p2 * (e + t) * e * (e - 2 * t)
+                    + p3 * (e + t) * e * (e - t);
+                n / (6 * t * t * t)
+            }
+        };
+
+        value as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODES: [Interpolation; 3] = [
+        Interpolation::Linear,
+        Interpolation::CubicHermite,
+        Interpolation::Lagrange4,
+    ];
+
+    #[test]
+    fn nodes_are_exact() {
+        for mode in MODES {
+            assert_eq!(mode.interpolate([-50, 100, 700, 20], 0, 3600), 100);
+            assert_eq!(mode.interpolate([-50, 100, 700, 20], 3600, 3600), 700);
+        }
+    }
+
+    #[test]
+    fn straight_lines_are_preserved() {
+        for mode in MODES {
+            for e in (0..=3600).step_by(300) {
+                assert_eq!(
+                    mode.interpolate([0, 1000, 2000, 3000], e, 3600),
+                    1000 + e * 1000 / 3600
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn cubic_schemes_follow_curvature() {
+        // samples of x^2 at x = -1, 0, 1, 2 (scaled by 1000)
+        let p = [1000, 0, 1000, 4000];
+        // value at x = 0.5 is 250
+        assert_eq!(Interpolation::Linear.interpolate(p, 1800, 3600), 500);
+        assert_eq!(Interpolation::CubicHermite.interpolate(p, 1800, 3600), 250);
+        assert_eq!(Interpolation::Lagrange4.interpolate(p, 1800, 3600), 250);
+    }
+}
@@ -1,9 +1,113 @@
+#![cfg_attr(not(test), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
 mod defs;
-pub use defs::MoonEphemeris;
+pub use defs::{Channel, Ephemeris, MoonEphemeris};
 
+#[cfg(all(feature = "bundled-data", not(feature = "generated-data")))]
 mod data;
+#[cfg(feature = "generated-data")]
+mod data {
+    include!(concat!(env!("OUT_DIR"), "/data.rs"));
+}
+#[cfg(feature = "bundled-data")]
 pub use data::MOON_EPHEMERIS;
 
+// build script table generator, compiled here for its tests only
+#[cfg(test)]
+mod generate;
+
+mod coverage;
+pub use coverage::{ChannelCoverage, Coverage};
+
+mod set;
+pub use set::{EphemerisSet, SetChannel};
+
+mod samples;
+pub use samples::{Entries, Samples};
+
+mod units;
+pub use units::{Centidegrees, Decidegrees, Degrees};
+
+mod rules;
+pub use rules::{
+    validate_tables, Violation, ViolationKind, ELEVATION_MAX, ELEVATION_RATE, SHADOW_FLIP,
+    SHADOW_MAX, SHADOW_RATE,
+};
+
+mod validate;
+pub use validate::{first_violation, validate, Violations};
+
+mod error;
+pub use error::EphemerisError;
+
+mod interpolation;
+pub use interpolation::Interpolation;
+
+mod compact;
+#[cfg(feature = "alloc")]
+pub use compact::CompactChannelBuf;
+pub use compact::{Block, CompactChannel, CompactEphemeris, BLOCK_LEN, MAX_WIDTH, SHADOW_MODULUS};
+
+mod lunar;
+pub use lunar::{analytic_shadow_angle_from_unix_timestamp, moon_position, EclipticPosition};
+
+mod time;
+pub use time::{
+    delta_t, julian_day, julian_day_tt, tai_minus_utc, tt_minus_utc, JulianDay, J2000_JD,
+    MJD_OFFSET, TT_MINUS_TAI_S, UNIX_EPOCH_JD,
+};
+
+#[cfg(feature = "chrono")]
+mod datetime;
+
+mod solar;
+pub use solar::{sun_position, AU_KM};
+
+mod observer;
+pub use observer::{
+    sidereal_time, topocentric_elevation_from_unix_timestamp, Horizontal, Observer,
+};
+
+mod orientation;
+
+mod twilight;
+pub use twilight::{sun_elevation_from_unix_timestamp, Twilight, SUNRISE_ELEVATION};
+
+mod rise;
+pub use rise::{
+    next_moonrise, next_moonset, next_transit, ElevationSource, HORIZON_REFRACTION, MOON_RADIUS_KM,
+    SEARCH_WINDOW_S,
+};
+
+mod distance;
+pub use distance::{
+    angular_diameter_at, distance_km_at, is_supermoon, next_apogee, next_apsis, next_perigee, Apsis,
+};
+
+mod phase;
+pub use phase::{next_phase_event, phase_events, PhaseEvent, PhaseEvents};
+
+mod state;
+pub use state::{moon_state_at, MoonState, ILLUMINATION_ONE, SYNODIC_MONTH_DAYS};
+
+#[cfg(feature = "std")]
+mod clock;
+#[cfg(feature = "std")]
+pub use clock::{moon_state_now, shadow_angle_now, unix_now, UnixTime};
+
+mod blob;
+#[cfg(feature = "alloc")]
+pub use blob::write_blob;
+pub use blob::{
+    crc32, BlobChannel, BlobError, EphemerisBlob, Location, SampleEncoding, BLOB_MAGIC,
+    BLOB_VERSION, HEADER_LEN,
+};
+
 /// Compute full modulo [0;+36000[ of provided angle in centidegrees
 fn modulo_full(mut a: i32) -> i32 {
     loop {
@@ -30,61 +134,121 @@ fn modulo_half_half(mut a: i32) -> i32 {
     }
 }
 
-/// Approximate angle at provided timestamp
+/// Approximate angle at provided timestamp using provided interpolation scheme
 /// Returned value is 10x stored unit
-pub fn approx_angle_from_unix_timestamp<T: Into<i32> + Copy>(
-    data: &MoonEphemeris,
-    angles: &[T],
+pub(crate) fn approx_angle<E: Ephemeris + ?Sized, C: Channel + ?Sized>(
+    data: &E,
+    angles: &C,
     unix: i64,
-) -> Option<i32> {
+    interpolation: Interpolation,
+) -> Result<i32, EphemerisError> {
+    // a zero period would make every entry share the same timestamp
+    if data.period() == 0 {
+        return Err(EphemerisError::InvalidPeriod);
+    }
+    if angles.is_empty() {
+        return Err(EphemerisError::EmptyTable);
+    }
+
     // offset timestamp by starting date in ephemerid
-    let offset_s = unix - (data.start as i64);
+    let offset_s = unix - (data.start() as i64);
 
     // if result is negative provided timestamp predate ephemerids
-    (offset_s >= 0).then_some(())?;
+    if offset_s < 0 {
+        return Err(EphemerisError::BeforeStart);
+    }
+
+    // last timestamp for which an entry is stored
+    let after_end = EphemerisError::AfterEnd {
+        last_valid: (data.start() as i64) + (data.period() as i64) * (angles.len() as i64 - 1),
+    };
+
+    // fetch an angle by index and convert it to 10x stored unit
+    let angle_at = |index: i64| -> Option<i32> {
+        let a = angles.sample(usize::try_from(index).ok()?)?;
+        Some(10 * a)
+    };
 
     // divide offset by ephemerid period to get angle index in array
-    let index = i64::div_euclid(offset_s, data.period as i64);
+    let index = i64::div_euclid(offset_s, data.period() as i64);
     // get previous  angle in centidegrees
-    let pa = *angles.get(index as usize)?;
-    let pa: i32 = 10 * pa.into();
+    let pa = angle_at(index).ok_or(after_end)?;
 
     // compute elapsed second since angle entry timestamp
-    let elapsed_s = i64::rem_euclid(offset_s, data.period as i64);
+    let elapsed_s = i64::rem_euclid(offset_s, data.period() as i64);
     let elapsed_s = elapsed_s as i32;
 
     // return previous shadow angle if offset is zero
     if elapsed_s == 0 {
-        return Some(pa);
+        return Ok(pa);
     }
 
     // get next shadow angle in centidegrees
-    let na = *angles.get((index + 1) as usize)?;
-    let na = 10 * na.into();
+    let na = angle_at(index + 1).ok_or(after_end)?;
 
     // compute angle delta, get [-pi;pi[ equivalent angle
     let delta = na - pa;
-    let delta = modulo_half_half(delta);
+    let na = pa + modulo_half_half(delta);
+
+    let points = match interpolation {
+        Interpolation::Linear => [pa, pa, na, na],
+        _ => {
+            // outer neighbours are unwrapped the same way, missing ones
+            // at both ends of the table are linearly extrapolated
+            let ppa = angle_at(index - 1)
+                .map(|a| pa + modulo_half_half(a - pa))
+                .unwrap_or(2 * pa - na);
+            let nna = angle_at(index + 2)
+                .map(|a| na + modulo_half_half(a - na))
+                .unwrap_or(2 * na - pa);
+            [ppa, pa, na, nna]
+        }
+    };
 
-    // use a linear ratio between previous and next angle using elapsed time
-    let offset = elapsed_s * delta / (data.period as i32);
+    Ok(interpolation.interpolate(points, elapsed_s, data.period() as i32))
+}
 
-    Some(pa + offset)
+/// Return moon shadow angle in centidegrees ranging [0,360[ at provided timestamp
+pub fn shadow_angle_from_unix_timestamp<E: Ephemeris + ?Sized>(
+    data: &E,
+    unix: i64,
+) -> Result<Centidegrees, EphemerisError> {
+    shadow_angle_from_unix_timestamp_with(data, unix, Interpolation::Linear)
 }
 
 /// Return moon shadow angle in centidegrees ranging [0,360[ at provided timestamp
-pub fn shadow_angle_from_unix_timestamp(data: &MoonEphemeris, unix: i64) -> Option<u32> {
-    approx_angle_from_unix_timestamp(data, data.shadow, unix).map(|angle|
+/// using provided interpolation scheme
+pub fn shadow_angle_from_unix_timestamp_with<E: Ephemeris + ?Sized>(
+    data: &E,
+    unix: i64,
+    interpolation: Interpolation,
+) -> Result<Centidegrees, EphemerisError> {
+    approx_angle(data, data.shadow(), unix, interpolation).map(|angle|
         // returned angle is in centidegrees ranging [0..360deg[
-        modulo_full(angle) as u32)
+        Centidegrees(modulo_full(angle)))
+}
+
+/// Return moon elevation angle in decidegrees ranging [-90,90[ at provided timestamp
+pub fn elevation_from_unix_timestamp<E: Ephemeris + ?Sized>(
+    data: &E,
+    unix: i64,
+) -> Result<Decidegrees, EphemerisError> {
+    elevation_from_unix_timestamp_with(data, unix, Interpolation::Linear)
 }
 
 /// Return moon elevation angle in decidegrees ranging [-90,90[ at provided timestamp
-pub fn elevation_from_unix_timestamp(data: &MoonEphemeris, unix: i64) -> Option<i32> {
-    approx_angle_from_unix_timestamp(data, data.elevation, unix)
+/// using provided interpolation scheme
+pub fn elevation_from_unix_timestamp_with<E: Ephemeris + ?Sized>(
+    data: &E,
+    unix: i64,
+    interpolation: Interpolation,
+) -> Result<Decidegrees, EphemerisError> {
+    approx_angle(data, data.elevation(), unix, interpolation).map(Decidegrees)
 }
 
 #[cfg(test)]
+// samples are spelled out as multiples of the table period
+#[allow(clippy::identity_op, clippy::erasing_op)]
 mod tests {
     use super::*;
 
@@ -116,57 +280,53 @@ mod tests {
         elevation: &[0, 90, 0, -90, 0],
     };
 
-    fn sa(unix: i64) -> Option<u32> {
-        shadow_angle_from_unix_timestamp(&EPHEMERIS, unix)
+    fn sa(unix: i64) -> Result<i32, EphemerisError> {
+        shadow_angle_from_unix_timestamp(&EPHEMERIS, unix).map(|a| a.0)
     }
 
-    fn ev(unix: i64) -> Option<i32> {
-        elevation_from_unix_timestamp(&EPHEMERIS, unix)
+    fn ev(unix: i64) -> Result<i32, EphemerisError> {
+        elevation_from_unix_timestamp(&EPHEMERIS, unix).map(|e| e.0)
     }
 
     #[test]
     fn shadow_simple_known_values() {
-        const N: usize = EPHEMERIS.shadow.len();
         const T0: i64 = EPHEMERIS.start as i64;
-        const P: u32 = EPHEMERIS.period;
 
-        assert_eq!(sa(T0 + 0 * 3600), Some(0));
-        assert_eq!(sa(T0 + 1 * 3600), Some(9000));
-        assert_eq!(sa(T0 + 2 * 3600), Some(18000));
-        assert_eq!(sa(T0 + 3 * 3600), Some(27000));
-        assert_eq!(sa(T0 + 4 * 3600), Some(0));
+        assert_eq!(sa(T0 + 0 * 3600), Ok(0));
+        assert_eq!(sa(T0 + 1 * 3600), Ok(9000));
+        assert_eq!(sa(T0 + 2 * 3600), Ok(18000));
+        assert_eq!(sa(T0 + 3 * 3600), Ok(27000));
+        assert_eq!(sa(T0 + 4 * 3600), Ok(0));
     }
 
     #[test]
     fn elevation_simple_known_values() {
         const T0: i64 = EPHEMERIS.start as i64;
 
-        assert_eq!(ev(T0 + 0 * 3600), Some(0));
-        assert_eq!(ev(T0 + 1 * 3600), Some(900));
-        assert_eq!(ev(T0 + 2 * 3600), Some(0));
-        assert_eq!(ev(T0 + 3 * 3600), Some(-900));
-        assert_eq!(ev(T0 + 4 * 3600), Some(0));
+        assert_eq!(ev(T0 + 0 * 3600), Ok(0));
+        assert_eq!(ev(T0 + 1 * 3600), Ok(900));
+        assert_eq!(ev(T0 + 2 * 3600), Ok(0));
+        assert_eq!(ev(T0 + 3 * 3600), Ok(-900));
+        assert_eq!(ev(T0 + 4 * 3600), Ok(0));
     }
 
     #[test]
     fn shadow_simple_intermediate_values() {
-        const N: usize = EPHEMERIS.shadow.len();
         const T0: i64 = EPHEMERIS.start as i64;
-        const P: u32 = EPHEMERIS.period;
 
-        assert_eq!(sa(T0 + 1 * 30 * 60), Some(4500));
-        assert_eq!(sa(T0 + 3 * 30 * 60), Some(13500));
-        assert_eq!(sa(T0 + 5 * 30 * 60), Some(22500));
+        assert_eq!(sa(T0 + 1 * 30 * 60), Ok(4500));
+        assert_eq!(sa(T0 + 3 * 30 * 60), Ok(13500));
+        assert_eq!(sa(T0 + 5 * 30 * 60), Ok(22500));
     }
 
     #[test]
     fn elevation_simple_intermediate_values() {
         const T0: i64 = EPHEMERIS.start as i64;
 
-        assert_eq!(ev(T0 + 1 * 30 * 60), Some(450));
-        assert_eq!(ev(T0 + 3 * 30 * 60), Some(450));
-        assert_eq!(ev(T0 + 5 * 30 * 60), Some(-450));
-        assert_eq!(ev(T0 + 7 * 30 * 60), Some(-450));
+        assert_eq!(ev(T0 + 1 * 30 * 60), Ok(450));
+        assert_eq!(ev(T0 + 3 * 30 * 60), Ok(450));
+        assert_eq!(ev(T0 + 5 * 30 * 60), Ok(-450));
+        assert_eq!(ev(T0 + 7 * 30 * 60), Ok(-450));
     }
 
     const EPHEMERIS2: MoonEphemeris = MoonEphemeris {
@@ -176,21 +336,46 @@ mod tests {
         elevation: &[],
     };
 
-    fn sa2(unix: i64) -> Option<u32> {
-        shadow_angle_from_unix_timestamp(&EPHEMERIS2, unix)
+    fn sa2(unix: i64) -> Result<i32, EphemerisError> {
+        shadow_angle_from_unix_timestamp(&EPHEMERIS2, unix).map(|a| a.0)
+    }
+
+    #[test]
+    fn shadow_only_table_is_not_expired() {
+        const T0: i64 = EPHEMERIS2.start as i64;
+
+        assert_eq!(EPHEMERIS2.end(), Some(T0 + 3600));
+        assert!(EPHEMERIS2.contains(T0 + 1800));
+        assert_eq!(EPHEMERIS2.remaining(T0 + 1800), 1800);
+        assert_eq!(EPHEMERIS2.remaining(T0 + 7200), 0);
+    }
+
+    #[test]
+    fn raw_angles_are_unwrapped() {
+        const T0: i64 = EPHEMERIS2.start as i64;
+        let shadow = EPHEMERIS2.shadow;
+
+        // raw angles are unwrapped, typed lookups wrap them
+        assert_eq!(
+            approx_angle(&EPHEMERIS2, shadow, T0 + 1800, Interpolation::Linear),
+            Ok(36000)
+        );
+        assert_eq!(
+            approx_angle(&EPHEMERIS2, shadow, T0, Interpolation::CubicHermite),
+            Ok(35000)
+        );
+        assert_eq!(sa2(T0 + 1800), Ok(0));
     }
 
     #[test]
     fn shadow_simple_crossover() {
-        const N: usize = EPHEMERIS2.shadow.len();
         const T0: i64 = EPHEMERIS2.start as i64;
-        const P: u32 = EPHEMERIS2.period;
 
-        assert_eq!(sa2(T0 + 0 * 60 * 60), Some(35000));
-        assert_eq!(sa2(T0 + 1 * 15 * 60), Some(35500));
-        assert_eq!(sa2(T0 + 1 * 30 * 60), Some(0));
-        assert_eq!(sa2(T0 + 1 * 45 * 60), Some(500));
-        assert_eq!(sa2(T0 + 1 * 60 * 60), Some(1000));
+        assert_eq!(sa2(T0 + 0 * 60 * 60), Ok(35000));
+        assert_eq!(sa2(T0 + 1 * 15 * 60), Ok(35500));
+        assert_eq!(sa2(T0 + 1 * 30 * 60), Ok(0));
+        assert_eq!(sa2(T0 + 1 * 45 * 60), Ok(500));
+        assert_eq!(sa2(T0 + 1 * 60 * 60), Ok(1000));
     }
 
     const EPHEMERIS3: MoonEphemeris = MoonEphemeris {
@@ -200,21 +385,148 @@ mod tests {
         elevation: &[],
     };
 
-    fn sa3(unix: i64) -> Option<u32> {
-        shadow_angle_from_unix_timestamp(&EPHEMERIS3, unix)
+    fn sa3(unix: i64) -> Result<i32, EphemerisError> {
+        shadow_angle_from_unix_timestamp(&EPHEMERIS3, unix).map(|a| a.0)
     }
 
     #[test]
     fn shadow_backward_crossover() {
-        const N: usize = EPHEMERIS3.shadow.len();
         const T0: i64 = EPHEMERIS3.start as i64;
-        const P: u32 = EPHEMERIS3.period;
 
-        assert_eq!(sa3(T0 + 0 * 60 * 60), Some(1000));
-        assert_eq!(sa3(T0 + 1 * 15 * 60), Some(500));
-        assert_eq!(sa3(T0 + 1 * 30 * 60), Some(0));
-        assert_eq!(sa3(T0 + 1 * 45 * 60), Some(35500));
-        assert_eq!(sa3(T0 + 1 * 60 * 60), Some(35000));
+        assert_eq!(sa3(T0 + 0 * 60 * 60), Ok(1000));
+        assert_eq!(sa3(T0 + 1 * 15 * 60), Ok(500));
+        assert_eq!(sa3(T0 + 1 * 30 * 60), Ok(0));
+        assert_eq!(sa3(T0 + 1 * 45 * 60), Ok(35500));
+        assert_eq!(sa3(T0 + 1 * 60 * 60), Ok(35000));
+    }
+
+    #[test]
+    fn lookup_errors() {
+        const T0: i64 = EPHEMERIS.start as i64;
+        const LAST: i64 = T0 + 4 * 3600;
+
+        assert_eq!(sa(T0 - 1), Err(EphemerisError::BeforeStart));
+        assert_eq!(sa(LAST), Ok(0));
+        assert_eq!(
+            sa(LAST + 1),
+            Err(EphemerisError::AfterEnd { last_valid: LAST })
+        );
+        assert_eq!(
+            sa(LAST + 3600),
+            Err(EphemerisError::AfterEnd { last_valid: LAST })
+        );
+
+        assert_eq!(
+            elevation_from_unix_timestamp(&EPHEMERIS2, T0),
+            Err(EphemerisError::EmptyTable)
+        );
+
+        const EPHEMERIS_NO_PERIOD: MoonEphemeris = MoonEphemeris {
+            start: 1234567,
+            period: 0,
+            shadow: &[0, 900],
+            elevation: &[0, 90],
+        };
+        assert_eq!(
+            shadow_angle_from_unix_timestamp(&EPHEMERIS_NO_PERIOD, T0),
+            Err(EphemerisError::InvalidPeriod)
+        );
+    }
+
+    #[test]
+    fn interpolation_crossover() {
+        const T0: i64 = EPHEMERIS2.start as i64;
+
+        for mode in [Interpolation::CubicHermite, Interpolation::Lagrange4] {
+            let sa2 = |unix| shadow_angle_from_unix_timestamp_with(&EPHEMERIS2, unix, mode);
+            assert_eq!(sa2(T0), Ok(Centidegrees(35000)));
+            assert_eq!(sa2(T0 + 1 * 30 * 60), Ok(Centidegrees(0)));
+            assert_eq!(sa2(T0 + 1 * 60 * 60), Ok(Centidegrees(1000)));
+        }
+    }
+
+    /// Keep one real entry out of `ratio`, skipped entries are used as reference
+    fn decimated(ratio: usize) -> MoonEphemeris {
+        MoonEphemeris {
+            start: MOON_EPHEMERIS.start,
+            period: MOON_EPHEMERIS.period * ratio as u32,
+            shadow: Vec::leak(
+                MOON_EPHEMERIS
+                    .shadow
+                    .iter()
+                    .step_by(ratio)
+                    .copied()
+                    .collect(),
+            ),
+            elevation: Vec::leak(
+                MOON_EPHEMERIS
+                    .elevation
+                    .iter()
+                    .step_by(ratio)
+                    .copied()
+                    .collect(),
+            ),
+        }
+    }
+
+    #[test]
+    fn interpolation_error_against_dense_samples() {
+        const RATIO: usize = 3;
+        const T0: i64 = MOON_EPHEMERIS.start as i64;
+        const P: i64 = MOON_EPHEMERIS.period as i64;
+        let sparse = decimated(RATIO);
+
+        // (max, rms) error in 10x stored unit for each mode
+        let mut errors = Vec::new();
+        for mode in [
+            Interpolation::Linear,
+            Interpolation::CubicHermite,
+            Interpolation::Lagrange4,
+        ] {
+            let mut max = [0; 2];
+            let mut sum_sq = [0i64; 2];
+            let mut count = 0;
+
+            for (i, (&s, &e)) in MOON_EPHEMERIS
+                .shadow
+                .iter()
+                .zip(MOON_EPHEMERIS.elevation)
+                .enumerate()
+                .filter(|(i, _)| i % RATIO != 0)
+            {
+                let ts = T0 + (i as i64) * P;
+                let (Ok(sa), Ok(ev)) = (
+                    shadow_angle_from_unix_timestamp_with(&sparse, ts, mode),
+                    elevation_from_unix_timestamp_with(&sparse, ts, mode),
+                ) else {
+                    continue;
+                };
+
+                let ds = modulo_half_half(sa.0 - 10 * s as i32).abs();
+                let de = (ev.0 - 10 * e as i32).abs();
+                for (k, d) in [ds, de].into_iter().enumerate() {
+                    max[k] = max[k].max(d);
+                    sum_sq[k] += (d as i64) * (d as i64);
+                }
+                count += 1;
+            }
+
+            let rms = sum_sq.map(|s| ((s / count) as f64).sqrt());
+            println!(
+                "{mode:?}: shadow max={} rms={:.1}, elevation max={} rms={:.1}",
+                max[0], rms[0], max[1], rms[1]
+            );
+            errors.push((max, rms));
+        }
+
+        let (linear_max, linear_rms) = errors[0];
+        for (max, rms) in &errors[1..] {
+            // higher order schemes follow elevation curvature better
+            assert!(rms[1] < linear_rms[1]);
+            assert!(max[1] < linear_max[1]);
+            // shadow angle is almost linear and must not degrade
+            assert!(rms[0] <= linear_rms[0] + 1.0);
+        }
     }
 
     #[test]
@@ -224,21 +536,12 @@ mod tests {
         const P: u32 = MOON_EPHEMERIS.period;
 
         // assert all values
-        let mut ts = T0;
-        for _ in 0..N {
-            let a = shadow_angle_from_unix_timestamp(&MOON_EPHEMERIS, ts);
-            assert!(a.is_some());
-            let a = a.unwrap();
-            println!("a={a}");
-            assert!(a < 36000);
-
-            let e = elevation_from_unix_timestamp(&MOON_EPHEMERIS, ts);
-            assert!(e.is_some());
-            let e = e.unwrap();
-            println!("e={e}");
-            assert!((-900 < e) && (e < 900));
-
-            ts += P as i64;
+        let end = T0 + P as i64 * N as i64;
+        for sample in MOON_EPHEMERIS.samples(T0..end, P) {
+            assert!(sample.is_ok());
+            let (_, a, e) = sample.unwrap();
+            assert!(a < Centidegrees(36000));
+            assert!((Decidegrees(-900) < e) && (e < Decidegrees(900)));
         }
     }
 }
@@ -0,0 +1,288 @@
+use libm::{acos, asin, atan2, cos, sin, tan};
+
+use crate::solar::sun_position;
+use crate::time::centuries;
+use crate::units::Centidegrees;
+
+/// Bring provided angle in degrees to [0;360[
+pub(crate) fn normalize_degrees(a: f64) -> f64 {
+    let a = a % 360.0;
+    if a < 0.0 {
+        a + 360.0
+    } else {
+        a
+    }
+}
+
+/// Geocentric position in the mean ecliptic of date
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EclipticPosition {
+    // ecliptic longitude in degrees ranging [0;360[
+    pub longitude: f64,
+    // ecliptic latitude in degrees
+    pub latitude: f64,
+    // distance from Earth center in kilometers
+    pub distance: f64,
+}
+
+impl EclipticPosition {
+    /// Rectangular coordinates in kilometers
+    pub fn xyz(&self) -> [f64; 3] {
+        let (l, b) = (self.longitude.to_radians(), self.latitude.to_radians());
+        [
+            self.distance * cos(b) * cos(l),
+            self.distance * cos(b) * sin(l),
+            self.distance * sin(b),
+        ]
+    }
+
+    /// Right ascension ranging [0;360[ and declination in degrees
+    pub(crate) fn equatorial(&self, unix: i64) -> (f64, f64) {
+        let eps = obliquity(centuries(unix)).to_radians();
+        let (l, b) = (self.longitude.to_radians(), self.latitude.to_radians());
+        let ra = atan2(sin(l) * cos(eps) - tan(b) * sin(eps), cos(l));
+        let dec = asin(sin(b) * cos(eps) + cos(b) * sin(eps) * sin(l));
+        (normalize_degrees(ra.to_degrees()), dec.to_degrees())
+    }
+}
+
+/// Periodic terms for Moon longitude and distance (Meeus table 47.A)
+/// multiples of D, M, M', F then longitude (1e-6 deg) and distance (1e-3 km)
+const LR_TERMS: [(i8, i8, i8, i8, i32, i32); 46] = [
+    (0, 0, 1, 0, 6288774, -20905355),
+    (2, 0, -1, 0, 1274027, -3699111),
+    (2, 0, 0, 0, 658314, -2955968),
+    (0, 0, 2, 0, 213618, -569925),
+    (0, 1, 0, 0, -185116, 48888),
+    (0, 0, 0, 2, -114332, -3149),
+    (2, 0, -2, 0, 58793, 246158),
+    (2, -1, -1, 0, 57066, -152138),
+    (2, 0, 1, 0, 53322, -170733),
+    (2, -1, 0, 0, 45758, -204586),
+    (0, 1, -1, 0, -40923, -129620),
+    (1, 0, 0, 0, -34720, 108743),
+    (0, 1, 1, 0, -30383, 104755),
+    (2, 0, 0, -2, 15327, 10321),
+    (0, 0, 1, 2, -12528, 0),
+    (0, 0, 1, -2, 10980, 79661),
+    (4, 0, -1, 0, 10675, -34782),
+    (0, 0, 3, 0, 10034, -23210),
+    (4, 0, -2, 0, 8548, -21636),
+    (2, 1, -1, 0, -7888, 24208),
+    (2, 1, 0, 0, -6766, 30824),
+    (1, 0, -1, 0, -5163, -8379),
+    (1, 1, 0, 0, 4987, -16675),
+    (2, -1, 1, 0, 4036, -12831),
+    (2, 0, 2, 0, 3994, -10445),
+    (4, 0, 0, 0, 3861, -11650),
+    (2, 0, -3, 0, 3665, 14403),
+    (0, 1, -2, 0, -2689, -7003),
+    (2, 0, -1, 2, -2602, 0),
+    (2, -1, -2, 0, 2390, 10056),
+    (1, 0, 1, 0, -2348, 6322),
+    (2, -2, 0, 0, 2236, -9884),
+    (0, 1, 2, 0, -2120, 5751),
+    (0, 2, 0, 0, -2069, 0),
+    (2, -2, -1, 0, 2048, -4950),
+    (2, 0, 1, -2, -1773, 4130),
+    (2, 0, 0, 2, -1595, 0),
+    (4, -1, -1, 0, 1215, -3958),
+    (0, 0, 2, 2, -1110, 0),
+    (3, 0, -1, 0, -892, 3258),
+    (2, 1, 1, 0, -810, 2616),
+    (4, -1, -2, 0, 759, -1897),
+    (0, 2, -1, 0, -713, -2117),
+    (2, 2, -1, 0, -700, 2354),
+    (2, 1, -2, 0, 691, 0),
+    (0, 0, 2, -2, -381, -4421),
+];
+
+/// Periodic terms for Moon latitude (Meeus table 47.B)
+/// multiples of D, M, M', F then latitude (1e-6 deg)
+const B_TERMS: [(i8, i8, i8, i8, i32); 30] = [
+    (0, 0, 0, 1, 5128122),
+    (0, 0, 1, 1, 280602),
+    (0, 0, 1, -1, 277693),
+    (2, 0, 0, -1, 173237),
+    (2, 0, -1, 1, 55413),
+    (2, 0, -1, -1, 46271),
+    (2, 0, 0, 1, 32573),
+    (0, 0, 2, 1, 17198),
+    (2, 0, 1, -1, 9266),
+    (0, 0, 2, -1, 8822),
+    (2, -1, 0, -1, 8216),
+    (2, 0, -2, -1, 4324),
+    (2, 0, 1, 1, 4200),
+    (2, 1, 0, -1, -3359),
+    (2, -1, -1, 1, 2463),
+    (2, -1, 0, 1, 2211),
+    (2, -1, -1, -1, 2065),
+    (0, 1, -1, -1, -1870),
+    (4, 0, -1, -1, 1828),
+    (0, 1, 0, 1, -1794),
+    (0, 0, 0, 3, -1749),
+    (0, 1, -1, 1, -1565),
+    (1, 0, 0, 1, -1491),
+    (0, 1, 1, 1, -1475),
+    (0, 1, 1, -1, -1410),
+    (0, 1, 0, -1, -1344),
+    (1, 0, 0, -1, -1335),
+    (0, 0, 3, 1, 1107),
+    (4, 0, 0, -1, 1021),
+    (4, 0, -1, 1, 833),
+];
+
+/// Geometric geocentric position of the Moon in the mean ecliptic of date
+///
+/// Truncated lunar theory (Meeus, Astronomical Algorithms, chapter 47),
+/// good to a few hundredths of a degree.
+pub fn moon_position(unix: i64) -> EclipticPosition {
+    let t = centuries(unix);
+    let (t2, t3, t4) = (t * t, t * t * t, t * t * t * t);
+
+    // mean longitude, mean elongation, Sun and Moon mean anomalies,
+    // Moon argument of latitude in degrees
+    let lp = 218.3164477 + 481267.88123421 * t - 0.0015786 * t2 + t3 / 538841.0 - t4 / 65194000.0;
+    let d = 297.8501921 + 445267.1114034 * t - 0.0018819 * t2 + t3 / 545868.0 - t4 / 113065000.0;
+    let m = 357.5291092 + 35999.0502909 * t - 0.0001536 * t2 + t3 / 24490000.0;
+    let mp = 134.9633964 + 477198.8675055 * t + 0.0087414 * t2 + t3 / 69699.0 - t4 / 14712000.0;
+    let f = 93.2720950 + 483202.0175233 * t - 0.0036539 * t2 - t3 / 3526000.0 + t4 / 863310000.0;
+
+    // additional arguments (action of Venus, Jupiter and Earth flattening)
+    let a1 = (119.75 + 131.849 * t).to_radians();
+    let a2 = (53.09 + 479264.290 * t).to_radians();
+    let a3 = (313.45 + 481266.484 * t).to_radians();
+
+    // decreasing Earth orbit eccentricity
+    let e = 1.0 - 0.002516 * t - 0.0000074 * t2;
+    let ecc = |m: i8| match m.abs() {
+        0 => 1.0,
+        1 => e,
+        _ => e * e,
+    };
+
+    let (lpr, dr, mr, mpr, fr) = (
+        lp.to_radians(),
+        d.to_radians(),
+        m.to_radians(),
+        mp.to_radians(),
+        f.to_radians(),
+    );
+    let argument = |kd: i8, km: i8, kmp: i8, kf: i8| {
+        kd as f64 * dr + km as f64 * mr + kmp as f64 * mpr + kf as f64 * fr
+    };
+
+    let mut sl = 0.0;
+    let mut sr = 0.0;
+    for (kd, km, kmp, kf, l, r) in LR_TERMS {
+        let a = argument(kd, km, kmp, kf);
+        sl += l as f64 * ecc(km) * sin(a);
+        sr += r as f64 * ecc(km) * cos(a);
+    }
+
+    let mut sb = 0.0;
+    for (kd, km, kmp, kf, b) in B_TERMS {
+        sb += b as f64 * ecc(km) * sin(argument(kd, km, kmp, kf));
+    }
+
+    sl += 3958.0 * sin(a1) + 1962.0 * sin(lpr - fr) + 318.0 * sin(a2);
+    sb += -2235.0 * sin(lpr)
+        + 382.0 * sin(a3)
+        + 175.0 * sin(a1 - fr)
+        + 175.0 * sin(a1 + fr)
+        + 127.0 * sin(lpr - mpr)
+        - 115.0 * sin(lpr + mpr);
+
+    EclipticPosition {
+        longitude: normalize_degrees(lp + sl / 1e6),
+        latitude: sb / 1e6,
+        distance: 385000.56 + sr / 1000.0,
+    }
+}
+
+/// Mean obliquity of the ecliptic in degrees
+pub(crate) fn obliquity(t: f64) -> f64 {
+    23.439291 - 0.0130042 * t
+}
+
+/// Compute moon shadow angle ranging [0,360[ at provided timestamp
+///
+/// Uses the same convention as `shadow_angle_from_unix_timestamp`: angle between
+/// Sun to Moon and Moon to Earth directions, signed by the equatorial north
+/// component of their cross product, zero at new moon, 180 degrees at full moon
+/// and decreasing with time.
+pub fn analytic_shadow_angle_from_unix_timestamp(unix: i64) -> Centidegrees {
+    let moon = moon_position(unix).xyz();
+    let sun = sun_position(unix).xyz();
+
+    // vectors from Sun to Moon and from Moon to Earth
+    let v1 = [moon[0] - sun[0], moon[1] - sun[1], moon[2] - sun[2]];
+    let v2 = [-moon[0], -moon[1], -moon[2]];
+
+    let norm = |v: [f64; 3]| libm::sqrt(v[0] * v[0] + v[1] * v[1] + v[2] * v[2]);
+    let dot = v1[0] * v2[0] + v1[1] * v2[1] + v1[2] * v2[2];
+    let alpha = acos((dot / (norm(v1) * norm(v2))).clamp(-1.0, 1.0));
+
+    // cross product expressed in ecliptic frame, only its component along
+    // the equatorial north pole is needed to sign the angle
+    let cy = v1[2] * v2[0] - v1[0] * v2[2];
+    let cz = v1[0] * v2[1] - v1[1] * v2[0];
+    let eps = obliquity(centuries(unix)).to_radians();
+    let north = cy * sin(eps) + cz * cos(eps);
+
+    // reference is the right side of moon shadow
+    let alpha = if north < 0.0 { alpha } else { -alpha };
+
+    let centidegrees = libm::round(normalize_degrees(alpha.to_degrees()) * 100.0) as i32;
+    Centidegrees(centidegrees % 36000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MOON_EPHEMERIS;
+
+    #[test]
+    fn meeus_example_47a() {
+        // 1992 April 12 0h TD
+        let unix = 703_036_800 - crate::time::tt_minus_utc(703_036_800) as i64;
+        let p = moon_position(unix);
+
+        assert!((p.longitude - 133.162655).abs() < 0.01);
+        assert!((p.latitude - -3.229126).abs() < 0.01);
+        assert!((p.distance - 368409.7).abs() < 20.0);
+    }
+
+    #[test]
+    fn shadow_angle_against_bundled_table() {
+        const T0: i64 = MOON_EPHEMERIS.start as i64;
+        const P: i64 = MOON_EPHEMERIS.period as i64;
+
+        let mut max = (0, T0);
+        let mut sum_sq = 0i64;
+        for (i, &s) in MOON_EPHEMERIS.shadow.iter().enumerate() {
+            let ts = T0 + i as i64 * P;
+            let a = analytic_shadow_angle_from_unix_timestamp(ts).0;
+            // table stores truncated decidegrees
+            let d = crate::modulo_half_half(a - 10 * s as i32 - 5).abs();
+            if d > max.0 {
+                max = (d, ts);
+            }
+            sum_sq += (d * d) as i64;
+
+            // the angle sign flips within an hour around new and full moons,
+            // model and table may then disagree on which side a sample lies
+            if d > 50 {
+                let from_syzygy = crate::modulo_half_half(2 * 10 * s as i32).abs() / 2;
+                assert!(from_syzygy < 1000, "error {d} at {ts}");
+            }
+        }
+        let rms = ((sum_sq / MOON_EPHEMERIS.shadow.len() as i64) as f64).sqrt();
+        println!(
+            "analytic model error: max {} centidegrees at {}, rms {rms:.1} centidegrees",
+            max.0, max.1
+        );
+
+        assert!(rms < 10.0);
+    }
+}
@@ -0,0 +1,185 @@
+use libm::{asin, atan, atan2, cos, round, sin, sqrt, tan};
+
+use crate::lunar::{moon_position, normalize_degrees, obliquity, EclipticPosition};
+use crate::time::{centuries, julian_day, J2000_JD};
+use crate::units::{Centidegrees, Decidegrees};
+
+/// Earth equatorial radius in kilometers
+const EARTH_RADIUS_KM: f64 = 6378.14;
+/// Earth polar to equatorial radius ratio
+const EARTH_AXIS_RATIO: f64 = 0.99664719;
+
+/// A place on Earth surface
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Observer {
+    // geodetic latitude in degrees, positive north
+    pub latitude: f64,
+    // longitude in degrees, positive east
+    pub longitude: f64,
+    // height above sea level in metres
+    pub height: f64,
+}
+
+/// Position in the observer local sky
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Horizontal {
+    // azimuth ranging [0;360[, measured from north towards east
+    pub azimuth: Centidegrees,
+    // geometric elevation above horizon ranging [-90;90]
+    pub elevation: Centidegrees,
+}
+
+impl Observer {
+    /// Observer position in kilometers in the equatorial frame of date
+    /// alongside its local zenith direction
+    fn geocentric(&self, unix: i64) -> ([f64; 3], [f64; 3]) {
+        let phi = self.latitude.to_radians();
+        // local sidereal time
+        let theta = (sidereal_degrees(unix) + self.longitude).to_radians();
+
+        // geocentric latitude terms on the reference ellipsoid
+        let u = atan(EARTH_AXIS_RATIO * tan(phi));
+        let h = self.height / (EARTH_RADIUS_KM * 1000.0);
+        let rho_sin = EARTH_AXIS_RATIO * sin(u) + h * sin(phi);
+        let rho_cos = cos(u) + h * cos(phi);
+
+        let position = [
+            EARTH_RADIUS_KM * rho_cos * cos(theta),
+            EARTH_RADIUS_KM * rho_cos * sin(theta),
+            EARTH_RADIUS_KM * rho_sin,
+        ];
+        let zenith = [cos(phi) * cos(theta), cos(phi) * sin(theta), sin(phi)];
+
+        (position, zenith)
+    }
+
+    /// Locate a body given by its geocentric position in the observer sky
+    pub fn horizontal(&self, position: &EclipticPosition, unix: i64) -> Horizontal {
+        let (azimuth, elevation) = self.horizontal_degrees(position, unix);
+        Horizontal {
+            azimuth: Centidegrees(round(100.0 * azimuth) as i32 % 36000),
+            elevation: Centidegrees(round(100.0 * elevation) as i32),
+        }
+    }
+
+    /// Azimuth and elevation in degrees, unrounded for the solvers
+    pub(crate) fn horizontal_degrees(&self, position: &EclipticPosition, unix: i64) -> (f64, f64) {
+        // rotate ecliptic coordinates to equatorial ones
+        let eps = obliquity(centuries(unix)).to_radians();
+        let [x, y, z] = position.xyz();
+        let body = [x, y * cos(eps) - z * sin(eps), y * sin(eps) + z * cos(eps)];
+
+        // body seen from observer instead of Earth center
+        let (origin, zenith) = self.geocentric(unix);
+        let v = [
+            body[0] - origin[0],
+            body[1] - origin[1],
+            body[2] - origin[2],
+        ];
+        let norm = sqrt(v[0] * v[0] + v[1] * v[1] + v[2] * v[2]);
+
+        let up = (v[0] * zenith[0] + v[1] * zenith[1] + v[2] * zenith[2]) / norm;
+
+        // local east and north directions
+        let theta = atan2(zenith[1], zenith[0]);
+        let east = [-sin(theta), cos(theta), 0.0];
+        let north = [
+            -zenith[2] * cos(theta),
+            -zenith[2] * sin(theta),
+            sqrt(zenith[0] * zenith[0] + zenith[1] * zenith[1]),
+        ];
+        let e = v[0] * east[0] + v[1] * east[1];
+        let n = v[0] * north[0] + v[1] * north[1] + v[2] * north[2];
+
+        (
+            normalize_degrees(atan2(e, n).to_degrees()),
+            asin(up.clamp(-1.0, 1.0)).to_degrees(),
+        )
+    }
+}
+
+/// Greenwich mean sidereal time ranging [0;360[
+pub fn sidereal_time(unix: i64) -> Centidegrees {
+    Centidegrees(round(100.0 * sidereal_degrees(unix)) as i32 % 36000)
+}
+
+/// Greenwich mean sidereal time in degrees ranging [0;360[
+pub(crate) fn sidereal_degrees(unix: i64) -> f64 {
+    // sidereal time follows Earth rotation, hence universal time
+    let d = julian_day(unix) - J2000_JD;
+    let t = d / 36525.0;
+    normalize_degrees(
+        280.46061837 + 360.98564736629 * d + 0.000387933 * t * t - t * t * t / 38710000.0,
+    )
+}
+
+/// Return moon topocentric elevation angle ranging [-90,90]
+/// seen by provided observer at provided timestamp
+///
+/// Elevation is geometric, no atmospheric refraction is applied.
+pub fn topocentric_elevation_from_unix_timestamp(observer: &Observer, unix: i64) -> Decidegrees {
+    let (_, elevation) = observer.horizontal_degrees(&moon_position(unix), unix);
+    Decidegrees(round(10.0 * elevation) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MOON_EPHEMERIS;
+
+    /// Location the bundled table elevation was computed for, see
+    /// ephemeris.conf
+    const TABLE_SITE: Observer = Observer {
+        latitude: 44.85,
+        longitude: -0.55,
+        height: 0.0,
+    };
+
+    #[test]
+    fn sidereal_time_known_value() {
+        // Meeus example 12.a, 1987 April 10 0h UT
+        assert!((sidereal_degrees(545_011_200) - 197.693195).abs() < 1e-4);
+        assert_eq!(sidereal_time(545_011_200), Centidegrees(19769));
+    }
+
+    #[test]
+    fn pole_sees_declination() {
+        let north_pole = Observer {
+            latitude: 90.0,
+            ..Default::default()
+        };
+        let unix = 1_762_879_028;
+        let p = moon_position(unix);
+        // at the pole elevation is the body declination
+        let eps = obliquity(centuries(unix)).to_radians();
+        let (l, b) = (p.longitude.to_radians(), p.latitude.to_radians());
+        let dec = asin(sin(b) * cos(eps) + cos(b) * sin(eps) * sin(l)).to_degrees();
+        let h = north_pole.horizontal(&p, unix).elevation.degrees();
+        // parallax lowers the moon by at most one degree
+        assert!(h < dec && h > dec - 1.0);
+    }
+
+    #[test]
+    fn elevation_against_bundled_table() {
+        const T0: i64 = MOON_EPHEMERIS.start as i64;
+        const P: i64 = MOON_EPHEMERIS.period as i64;
+
+        let mut max = 0;
+        for (i, &e) in MOON_EPHEMERIS.elevation.iter().enumerate() {
+            let ts = T0 + i as i64 * P;
+            let a = topocentric_elevation_from_unix_timestamp(&TABLE_SITE, ts).0;
+
+            // table stores whole degrees truncated towards zero
+            let e = 10 * e as i32;
+            let d = match e {
+                0 => (a.abs() - 10).max(0),
+                e if e > 0 => (e - a).max(a - e - 10),
+                e => (a - e).max(e - a - 10),
+            };
+            max = max.max(d);
+        }
+        println!("topocentric elevation error: max {max} decidegrees");
+
+        assert!(max <= 2);
+    }
+}
@@ -0,0 +1,80 @@
+use libm::{atan2, cos, round, sin, tan};
+
+use crate::lunar::{moon_position, normalize_degrees};
+use crate::observer::{sidereal_degrees, Observer};
+use crate::state::bright_limb_angle;
+use crate::units::Centidegrees;
+
+impl Observer {
+    /// Moon hour angle in degrees ranging [0;360[, increasing westwards,
+    /// alongside its declination
+    fn moon_hour_angle(&self, unix: i64) -> (f64, f64) {
+        let (ra, dec) = moon_position(unix).equatorial(unix);
+        (
+            normalize_degrees(sidereal_degrees(unix) + self.longitude - ra),
+            dec,
+        )
+    }
+
+    /// Parallactic angle of the moon ranging ]-180;180], angle between
+    /// celestial north and the zenith direction at the moon disc
+    ///
+    /// Negative while the moon rises in the east, zero at upper transit for
+    /// observers north of the moon and positive once it heads west (Meeus
+    /// 14.1).
+    pub fn parallactic_angle(&self, unix: i64) -> Centidegrees {
+        let (h, dec) = self.moon_hour_angle(unix);
+        let (h, dec, phi) = (h.to_radians(), dec.to_radians(), self.latitude.to_radians());
+        let q = atan2(sin(h), tan(phi) * cos(dec) - sin(dec) * cos(h));
+        Centidegrees(round(100.0 * q.to_degrees()) as i32)
+    }
+
+    /// Position angle of the bright limb midpoint as seen by observer,
+    /// ranging [0;360[ and measured from the zenith towards the left
+    ///
+    /// The terminator runs perpendicular to this direction. It reads 0 when
+    /// the lit side faces up, and 180 for the crescent "smile" seen near the
+    /// equator once the sun has set below the moon.
+    pub fn terminator_orientation(&self, unix: i64) -> Centidegrees {
+        let q = self.parallactic_angle(unix).degrees();
+        let angle = normalize_degrees(bright_limb_angle(unix) - q);
+        Centidegrees(round(100.0 * angle) as i32 % 36000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::next_transit;
+
+    #[test]
+    fn parallactic_angle_around_transit() {
+        let site = Observer {
+            latitude: 51.5,
+            ..Default::default()
+        };
+        let transit = next_transit(&site, 1_762_879_028).unwrap().unwrap();
+
+        // moon culminates south of the zenith, sky north points straight up
+        assert!(site.parallactic_angle(transit).abs() < Centidegrees(100));
+        assert!(site.parallactic_angle(transit - 3 * 3600) < Centidegrees(-1000));
+        assert!(site.parallactic_angle(transit + 3 * 3600) > Centidegrees(1000));
+    }
+
+    #[test]
+    fn crescent_tilt_depends_on_latitude() {
+        // 2025-11-22 19:00 UTC, two day old crescent setting after the sun
+        let unix = 1_763_838_000;
+        let tilt = |latitude: f64| {
+            let site = Observer {
+                latitude,
+                ..Default::default()
+            };
+            (site.terminator_orientation(unix) - Centidegrees(18000)).abs()
+        };
+
+        // close to a smile near the equator, tilted further north
+        assert!(tilt(0.0) < Centidegrees(2000));
+        assert!(tilt(30.0) > tilt(0.0) + Centidegrees(2000));
+    }
+}
@@ -0,0 +1,324 @@
+use core::ops::Range;
+
+use crate::defs::Ephemeris;
+use crate::error::EphemerisError;
+use crate::lunar::{moon_position, normalize_degrees};
+use crate::solar::sun_position;
+use crate::units::Centidegrees;
+use crate::{modulo_half_half, shadow_angle_from_unix_timestamp};
+
+/// Annual aberration shifting the apparent Sun longitude, in degrees
+const SUN_ABERRATION: f64 = 0.00569;
+/// Half width of the window an event estimate is refined in, in seconds
+const REFINE_WINDOW_S: i64 = 6 * 3600;
+
+/// Principal moon phases
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhaseEvent {
+    NewMoon,
+    FirstQuarter,
+    FullMoon,
+    LastQuarter,
+}
+
+impl PhaseEvent {
+    pub const ALL: [PhaseEvent; 4] = [
+        PhaseEvent::NewMoon,
+        PhaseEvent::FirstQuarter,
+        PhaseEvent::FullMoon,
+        PhaseEvent::LastQuarter,
+    ];
+
+    /// Elongation of the Moon from the Sun in degrees at this phase
+    fn elongation(self) -> f64 {
+        match self {
+            PhaseEvent::NewMoon => 0.0,
+            PhaseEvent::FirstQuarter => 90.0,
+            PhaseEvent::FullMoon => 180.0,
+            PhaseEvent::LastQuarter => 270.0,
+        }
+    }
+
+    /// Shadow angle reached at this phase
+    ///
+    /// Shadow angle decreases with time, from 360 degrees right after
+    /// new moon down to 0 at the next one.
+    pub fn shadow_angle(self) -> Centidegrees {
+        match self {
+            PhaseEvent::NewMoon => Centidegrees(0),
+            PhaseEvent::FirstQuarter => Centidegrees(27000),
+            PhaseEvent::FullMoon => Centidegrees(18000),
+            PhaseEvent::LastQuarter => Centidegrees(9000),
+        }
+    }
+}
+
+/// Refine a phase event time estimated from the shadow angle
+///
+/// Published phases are defined on geocentric ecliptic longitudes while the
+/// shadow angle sign flips on equatorial conjunctions, which can shift new
+/// and full moons by a few hours. The estimate is refined with the analytic
+/// model by solving for the Moon elongation instead, the table is not used
+/// past this point.
+fn refine(event: PhaseEvent, estimate: i64) -> i64 {
+    // apparent elongation distance to the event, increasing with time
+    let f = |t: i64| {
+        let elongation = moon_position(t).longitude - sun_position(t).longitude + SUN_ABERRATION;
+        normalize_degrees(elongation - event.elongation() + 180.0) - 180.0
+    };
+
+    let (mut lo, mut hi) = (estimate - REFINE_WINDOW_S, estimate + REFINE_WINDOW_S);
+    if f(lo) > 0.0 || f(hi) < 0.0 {
+        return estimate;
+    }
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if f(mid) < 0.0 {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    hi
+}
+
+/// Find first phase event in ]after;until], return None if there is none
+fn next_phase_event_until<E: Ephemeris + ?Sized>(
+    data: &E,
+    after: i64,
+    until: i64,
+) -> Result<Option<(PhaseEvent, i64)>, EphemerisError> {
+    if data.period() == 0 {
+        return Err(EphemerisError::InvalidPeriod);
+    }
+    let step = data.period() as i64;
+
+    // signed distance in centidegrees between shadow angle and event angle,
+    // turns from positive to negative when event happens
+    let distance =
+        |angle: Centidegrees, event: PhaseEvent| modulo_half_half((angle - event.shadow_angle()).0);
+    let crosses = |a: i32, b: i32| a > 0 && b <= 0 && a - b < 18000;
+
+    // refinement moves events by up to REFINE_WINDOW_S, table crossings are
+    // searched that much around ]after;until] as long as the table covers it
+    let last = data.coverage().shadow.map_or(until, |c| c.last.max(until));
+    let search_until = until.saturating_add(REFINE_WINDOW_S).min(last);
+    let mut t0 = (after - REFINE_WINDOW_S).max((data.start() as i64).min(after));
+    let mut a0 = shadow_angle_from_unix_timestamp(data, t0)?;
+    while t0 < search_until {
+        // bracket on entry timestamps so lookups stay linear in each bracket
+        let t1 = (t0 + step - (t0 - data.start() as i64).rem_euclid(step)).min(search_until);
+        let a1 = shadow_angle_from_unix_timestamp(data, t1)?;
+
+        let mut found: Option<(PhaseEvent, i64)> = None;
+        for event in PhaseEvent::ALL {
+            if !crosses(distance(a0, event), distance(a1, event)) {
+                continue;
+            }
+
+            // bisect bracket down to the second
+            let (mut lo, mut hi) = (t0, t1);
+            while hi - lo > 1 {
+                let mid = lo + (hi - lo) / 2;
+                let d = distance(shadow_angle_from_unix_timestamp(data, mid)?, event);
+                if d > 0 {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+
+            // an event refined before the search start was already reported
+            let t = refine(event, hi);
+            if t <= after || t > until {
+                continue;
+            }
+            match found {
+                Some((_, f)) if f <= t => {}
+                _ => found = Some((event, t)),
+            }
+        }
+        if found.is_some() {
+            return Ok(found);
+        }
+
+        (t0, a0) = (t1, a1);
+    }
+
+    Ok(None)
+}
+
+/// Return the first principal phase reached after provided timestamp
+///
+/// Events are bracketed by root finding on the table shadow angle, then
+/// refined with the analytic lunar and solar model rather than the table so
+/// they agree with published phase tables, see `refine`. Returned times may
+/// thus differ by a few hours from the table shadow angle crossing.
+pub fn next_phase_event<E: Ephemeris + ?Sized>(
+    data: &E,
+    after: i64,
+) -> Result<(PhaseEvent, i64), EphemerisError> {
+    next_phase_event_until(data, after, i64::MAX).map(|event| {
+        event.expect("unbounded search ends on an event or on a lookup past the table end")
+    })
+}
+
+/// Iterate over every principal phase happening in provided range
+pub fn phase_events<E: Ephemeris + ?Sized>(data: &E, range: Range<i64>) -> PhaseEvents<'_, E> {
+    PhaseEvents {
+        data,
+        // events are searched strictly after cursor
+        cursor: (range.start - 1).max(data.start() as i64),
+        end: range.end,
+        done: false,
+    }
+}
+
+/// Iterator returned by `phase_events`
+pub struct PhaseEvents<'a, E: ?Sized> {
+    data: &'a E,
+    cursor: i64,
+    end: i64,
+    done: bool,
+}
+
+impl<E: Ephemeris + ?Sized> Iterator for PhaseEvents<'_, E> {
+    type Item = Result<(PhaseEvent, i64), EphemerisError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match next_phase_event_until(self.data, self.cursor, self.end - 1) {
+            Ok(Some((event, t))) if t < self.end => {
+                self.cursor = t;
+                Some(Ok((event, t)))
+            }
+            Ok(_) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MoonEphemeris, MOON_EPHEMERIS};
+
+    #[test]
+    fn simple_events() {
+        const EPHEMERIS: MoonEphemeris = MoonEphemeris {
+            start: 1000,
+            period: 3600,
+            shadow: &[100, 3500, 2750, 2650, 1850, 1750],
+            elevation: &[],
+        };
+
+        let t0 = EPHEMERIS.start as i64;
+        assert_eq!(
+            next_phase_event(&EPHEMERIS, t0),
+            Ok((PhaseEvent::NewMoon, t0 + 1800))
+        );
+        assert_eq!(
+            next_phase_event(&EPHEMERIS, t0 + 1800),
+            Ok((PhaseEvent::FirstQuarter, t0 + 2 * 3600 + 1800))
+        );
+        assert_eq!(
+            next_phase_event(&EPHEMERIS, t0 + 3 * 3600),
+            Ok((PhaseEvent::FullMoon, t0 + 4 * 3600 + 1800))
+        );
+        assert_eq!(
+            next_phase_event(&EPHEMERIS, t0 + 5 * 3600),
+            Err(EphemerisError::AfterEnd {
+                last_valid: t0 + 5 * 3600
+            })
+        );
+
+        let events: Vec<_> = phase_events(&EPHEMERIS, t0..t0 + 5 * 3600)
+            .map(Result::unwrap)
+            .map(|(e, _)| e)
+            .collect();
+        assert_eq!(
+            events,
+            [
+                PhaseEvent::NewMoon,
+                PhaseEvent::FirstQuarter,
+                PhaseEvent::FullMoon
+            ]
+        );
+    }
+
+    // published UTC times of principal phases
+    const PUBLISHED: [(PhaseEvent, i64); 8] = [
+        (PhaseEvent::NewMoon, 1763621220),      // 2025-11-20 06:47
+        (PhaseEvent::FirstQuarter, 1764313140), // 2025-11-28 06:59
+        (PhaseEvent::FullMoon, 1764890040),     // 2025-12-04 23:14
+        (PhaseEvent::LastQuarter, 1765486320),  // 2025-12-11 20:52
+        (PhaseEvent::NewMoon, 1766194980),      // 2025-12-20 01:43
+        (PhaseEvent::FirstQuarter, 1766862600), // 2025-12-27 19:10
+        (PhaseEvent::FullMoon, 1767434580),     // 2026-01-03 10:03
+        (PhaseEvent::LastQuarter, 1768060080),  // 2026-01-10 15:48
+    ];
+
+    #[test]
+    fn published_phases() {
+        let start = PUBLISHED[0].1 - 86400;
+        let end = PUBLISHED[7].1 + 86400;
+        let events: Vec<_> = phase_events(&MOON_EPHEMERIS, start..end)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(events.len(), PUBLISHED.len());
+
+        for ((event, t), (published, p)) in events.into_iter().zip(PUBLISHED) {
+            assert_eq!(event, published);
+            assert!((t - p).abs() < 3 * 60);
+        }
+    }
+
+    #[test]
+    fn searches_starting_just_before_an_event_find_it() {
+        for (published, p) in PUBLISHED {
+            let (event, t) = next_phase_event(&MOON_EPHEMERIS, p - 3 * 60).unwrap();
+            assert_eq!(event, published, "{p}");
+            assert!((t - p).abs() < 3 * 60);
+        }
+
+        // every event of a year, including those refined hours away from
+        // their table crossing
+        let start = MOON_EPHEMERIS.start as i64;
+        let events: Vec<_> = phase_events(&MOON_EPHEMERIS, start..start + 365 * 86400)
+            .map(Result::unwrap)
+            .collect();
+        for (event, t) in events {
+            assert_eq!(next_phase_event(&MOON_EPHEMERIS, t - 60), Ok((event, t)));
+            let mut from_range = phase_events(&MOON_EPHEMERIS, t - 60..t + 60);
+            assert_eq!(from_range.next(), Some(Ok((event, t))));
+            assert_eq!(from_range.next(), None);
+        }
+    }
+
+    #[test]
+    fn a_lunation_has_four_phases() {
+        let start = MOON_EPHEMERIS.start as i64;
+        let events: Vec<_> = phase_events(&MOON_EPHEMERIS, start..start + 365 * 86400)
+            .map(Result::unwrap)
+            .collect();
+
+        // one year holds 12 or 13 lunations
+        assert!(events.len() >= 48 && events.len() <= 53);
+        for pair in events.windows(2) {
+            let ((e0, t0), (e1, t1)) = (pair[0], pair[1]);
+            let i0 = PhaseEvent::ALL.iter().position(|&e| e == e0).unwrap();
+            assert_eq!(PhaseEvent::ALL[(i0 + 1) % 4], e1);
+            // quarters are 6 to 9 days apart
+            assert!(t1 - t0 > 6 * 86400 && t1 - t0 < 9 * 86400);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use libm::asin;
+
+use crate::defs::Ephemeris;
+use crate::elevation_from_unix_timestamp_with;
+use crate::error::EphemerisError;
+use crate::interpolation::Interpolation;
+use crate::lunar::moon_position;
+use crate::observer::Observer;
+use crate::units::Centidegrees;
+
+/// Standard atmospheric refraction at the horizon, 34 arcminutes
+pub const HORIZON_REFRACTION: Centidegrees = Centidegrees(57);
+/// Mean Moon radius in kilometers
+pub const MOON_RADIUS_KM: f64 = 1737.4;
+
+/// Step used to bracket horizon crossings in seconds
+const BRACKET_STEP_S: i64 = 600;
+/// Events are looked for at most this long after the requested timestamp
+pub const SEARCH_WINDOW_S: i64 = 32 * 24 * 3600;
+
+/// Anything able to give the moon geometric topocentric elevation
+pub trait ElevationSource {
+    /// Moon center elevation at provided timestamp
+    fn moon_elevation(&self, unix: i64) -> Result<Centidegrees, EphemerisError>;
+
+    /// Moon center elevation in degrees, as precise as the source allows
+    ///
+    /// Solvers use it so rounding does not hide the slow elevation changes
+    /// around transit.
+    #[doc(hidden)]
+    fn moon_elevation_degrees(&self, unix: i64) -> Result<f64, EphemerisError> {
+        self.moon_elevation(unix).map(Centidegrees::degrees)
+    }
+}
+
+/// Tables give the elevation seen from the location they were computed for
+impl<E: Ephemeris + ?Sized> ElevationSource for E {
+    fn moon_elevation(&self, unix: i64) -> Result<Centidegrees, EphemerisError> {
+        elevation_from_unix_timestamp_with(self, unix, Interpolation::CubicHermite)
+            .map(Centidegrees::from)
+    }
+}
+
+/// Observers get their elevation from the analytic lunar model
+impl ElevationSource for Observer {
+    fn moon_elevation(&self, unix: i64) -> Result<Centidegrees, EphemerisError> {
+        Ok(self.horizontal(&moon_position(unix), unix).elevation)
+    }
+
+    fn moon_elevation_degrees(&self, unix: i64) -> Result<f64, EphemerisError> {
+        Ok(self.horizontal_degrees(&moon_position(unix), unix).1)
+    }
+}
+
+/// Elevation of the moon center when its upper limb touches the horizon
+fn horizon_elevation(unix: i64) -> f64 {
+    let semidiameter = asin(MOON_RADIUS_KM / moon_position(unix).distance).to_degrees();
+    -HORIZON_REFRACTION.degrees() - semidiameter
+}
+
+/// Find the first timestamp after `after` where `f` changes sign in the
+/// requested direction, refined to the second
+fn next_crossing<F>(after: i64, rising: bool, mut f: F) -> Result<Option<i64>, EphemerisError>
+where
+    F: FnMut(i64) -> Result<f64, EphemerisError>,
+{
+    let crosses = |a: f64, b: f64| match rising {
+        true => a < 0.0 && b >= 0.0,
+        false => a >= 0.0 && b < 0.0,
+    };
+
+    let mut t0 = after;
+    let mut f0 = f(t0)?;
+    while t0 < after + SEARCH_WINDOW_S {
+        let t1 = t0 + BRACKET_STEP_S;
+        let f1 = f(t1)?;
+
+        if crosses(f0, f1) {
+            // bisect bracket down to the second
+            let (mut lo, mut hi, mut flo) = (t0, t1, f0);
+            while hi - lo > 1 {
+                let mid = lo + (hi - lo) / 2;
+                let fmid = f(mid)?;
+                if crosses(flo, fmid) {
+                    hi = mid;
+                } else {
+                    (lo, flo) = (mid, fmid);
+                }
+            }
+            return Ok(Some(hi));
+        }
+
+        (t0, f0) = (t1, f1);
+    }
+
+    Ok(None)
+}
+
+/// Return timestamp of the first moonrise after provided timestamp
+///
+/// Moonrise happens when the upper limb of the moon appears above the
+/// horizon, accounting for standard refraction and the moon semidiameter.
+/// Return None if the moon does not rise within `SEARCH_WINDOW_S`.
+pub fn next_moonrise<S: ElevationSource + ?Sized>(
+    source: &S,
+    after: i64,
+) -> Result<Option<i64>, EphemerisError> {
+    next_crossing(after, true, |t| {
+        Ok(source.moon_elevation_degrees(t)? - horizon_elevation(t))
+    })
+}
+
+/// Return timestamp of the first moonset after provided timestamp
+///
+/// Moonset happens when the upper limb of the moon disappears below the
+/// horizon, accounting for standard refraction and the moon semidiameter.
+/// Return None if the moon does not set within `SEARCH_WINDOW_S`.
+pub fn next_moonset<S: ElevationSource + ?Sized>(
+    source: &S,
+    after: i64,
+) -> Result<Option<i64>, EphemerisError> {
+    next_crossing(after, false, |t| {
+        Ok(source.moon_elevation_degrees(t)? - horizon_elevation(t))
+    })
+}
+
+/// Return timestamp of the first upper transit after provided timestamp
+///
+/// Upper transit is found as the moment the moon elevation stops increasing.
+/// Return None if no transit happens within `SEARCH_WINDOW_S`.
+pub fn next_transit<S: ElevationSource + ?Sized>(
+    source: &S,
+    after: i64,
+) -> Result<Option<i64>, EphemerisError> {
+    // elevation slope over a minute, negative once the moon culminated
+    const H: i64 = 30;
+    next_crossing(after + H, false, |t| {
+        Ok(source.moon_elevation_degrees(t + H)? - source.moon_elevation_degrees(t - H)?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MOON_EPHEMERIS;
+
+    /// Location the bundled table elevation was computed for, see
+    /// ephemeris.conf
+    const TABLE_SITE: Observer = Observer {
+        latitude: 44.85,
+        longitude: -0.55,
+        height: 0.0,
+    };
+
+    #[test]
+    fn rise_transit_set_order() {
+        let start = MOON_EPHEMERIS.start as i64;
+
+        let rise = next_moonrise(&TABLE_SITE, start).unwrap().unwrap();
+        let transit = next_transit(&TABLE_SITE, rise).unwrap().unwrap();
+        let set = next_moonset(&TABLE_SITE, transit).unwrap().unwrap();
+        assert!(rise < transit && transit < set);
+        // the moon stays above horizon for about half a day
+        assert!(set - rise > 8 * 3600 && set - rise < 17 * 3600);
+
+        // elevation at transit is a local maximum
+        let e = |t| TABLE_SITE.moon_elevation_degrees(t).unwrap();
+        assert!(e(transit) >= e(transit - 600) && e(transit) >= e(transit + 600));
+        // upper limb lies on the refracted horizon at rise and set
+        for t in [rise, set] {
+            assert!((e(t) - horizon_elevation(t)).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn table_and_model_agree() {
+        let start = MOON_EPHEMERIS.start as i64;
+
+        let mut after = start;
+        for _ in 0..30 {
+            let model = next_moonrise(&TABLE_SITE, after).unwrap().unwrap();
+            let table = next_moonrise(&MOON_EPHEMERIS, after).unwrap().unwrap();
+            // table stores truncated whole degrees, moon rises about
+            // a degree every 7 minutes at this latitude
+            assert!((model - table).abs() < 20 * 60, "{model} {table}");
+            after = model + 3600;
+        }
+    }
+
+    #[test]
+    fn table_end_is_reported() {
+        let last = MOON_EPHEMERIS.start as i64
+            + MOON_EPHEMERIS.period as i64 * (MOON_EPHEMERIS.elevation.len() as i64 - 1);
+        assert_eq!(
+            next_moonset(&MOON_EPHEMERIS, last),
+            Err(EphemerisError::AfterEnd { last_valid: last })
+        );
+    }
+
+    #[test]
+    fn polar_moon_stays_up_for_days() {
+        // near the pole the moon stays above horizon for more than a week
+        // once risen
+        let pole = Observer {
+            latitude: 89.0,
+            ..Default::default()
+        };
+        let start = MOON_EPHEMERIS.start as i64;
+        let rise = next_moonrise(&pole, start).unwrap().unwrap();
+        let set = next_moonset(&pole, rise).unwrap().unwrap();
+        assert!(set - rise > 7 * 24 * 3600);
+        for t in (rise + 3600..set - 3600).step_by(3600) {
+            assert!(pole.moon_elevation(t).unwrap().degrees() > horizon_elevation(t));
+        }
+    }
+}
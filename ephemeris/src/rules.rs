@@ -0,0 +1,161 @@
+//! Sanity rules ephemeris tables must follow
+//!
+//! Rules only work on plain slices so they can run in const context and from
+//! the build script generating the bundled table.
+
+use core::fmt;
+
+/// Largest stored shadow angle in decidegrees
+pub const SHADOW_MAX: i32 = 3599;
+/// Largest stored absolute elevation in degrees
+pub const ELEVATION_MAX: i32 = 90;
+/// Largest shadow angle change in decidegrees for each hour of period
+pub const SHADOW_RATE: i32 = 10;
+/// Shadow angle jump in decidegrees allowed when its sign flips around new
+/// and full moons, about twice the largest Moon ecliptic latitude
+pub const SHADOW_FLIP: i32 = 110;
+/// Largest elevation change in degrees for each hour of period
+pub const ELEVATION_RATE: i32 = 16;
+
+/// What is wrong with an ephemeris table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// ephemeris period is zero
+    InvalidPeriod,
+    /// channels hold a different number of samples
+    LengthMismatch { shadow: usize, elevation: usize },
+    /// stored shadow angle is outside [0;SHADOW_MAX]
+    ShadowOutOfRange(i32),
+    /// stored elevation is outside [-ELEVATION_MAX;ELEVATION_MAX]
+    ElevationOutOfRange(i32),
+    /// shadow angle moved more than possible since previous sample
+    ShadowJump(i32),
+    /// elevation moved more than possible since previous sample
+    ElevationJump(i32),
+}
+
+/// A rule violation and the sample it was found at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    // index of the offending sample
+    pub index: usize,
+    // timestamp of the offending sample
+    pub unix: i64,
+    pub kind: ViolationKind,
+}
+
+impl Violation {
+    pub(crate) const fn at(start: u64, period: u32, index: usize, kind: ViolationKind) -> Self {
+        Violation {
+            index,
+            unix: start as i64 + period as i64 * index as i64,
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sample {} at {}: ", self.index, self.unix)?;
+        match self.kind {
+            ViolationKind::InvalidPeriod => write!(f, "period is zero"),
+            ViolationKind::LengthMismatch { shadow, elevation } => write!(
+                f,
+                "{shadow} shadow samples but {elevation} elevation samples"
+            ),
+            ViolationKind::ShadowOutOfRange(v) => write!(f, "shadow angle {v} out of range"),
+            ViolationKind::ElevationOutOfRange(v) => write!(f, "elevation {v} out of range"),
+            ViolationKind::ShadowJump(d) => write!(f, "shadow angle jumped by {d}"),
+            ViolationKind::ElevationJump(d) => write!(f, "elevation jumped by {d}"),
+        }
+    }
+}
+
+/// Largest change allowed over one period given a rate per hour
+const fn max_step(base: i32, rate: i32, period: u32) -> i64 {
+    // round up partial hours
+    base as i64 + (rate as i64 * period as i64 + 3599) / 3600
+}
+
+/// Check a shadow angle sample in decidegrees against the previous one
+pub(crate) const fn check_shadow(
+    prev: Option<i32>,
+    value: i32,
+    period: u32,
+) -> Option<ViolationKind> {
+    if value < 0 || value > SHADOW_MAX {
+        return Some(ViolationKind::ShadowOutOfRange(value));
+    }
+    if let Some(prev) = prev {
+        // angles wrap around a full turn
+        let d = (value - prev + 1800).rem_euclid(3600) - 1800;
+        if d.abs() as i64 > max_step(SHADOW_FLIP, SHADOW_RATE, period) {
+            return Some(ViolationKind::ShadowJump(d));
+        }
+    }
+    None
+}
+
+/// Check an elevation sample in degrees against the previous one
+pub(crate) const fn check_elevation(
+    prev: Option<i32>,
+    value: i32,
+    period: u32,
+) -> Option<ViolationKind> {
+    if value < -ELEVATION_MAX || value > ELEVATION_MAX {
+        return Some(ViolationKind::ElevationOutOfRange(value));
+    }
+    if let Some(prev) = prev {
+        // stored values are truncated to whole degrees
+        let d = value - prev;
+        if d.abs() as i64 > max_step(1, ELEVATION_RATE, period) {
+            return Some(ViolationKind::ElevationJump(d));
+        }
+    }
+    None
+}
+
+/// Return the first rule violation found in provided tables
+pub const fn validate_tables(
+    start: u64,
+    period: u32,
+    shadow: &[u16],
+    elevation: &[i8],
+) -> Option<Violation> {
+    if period == 0 {
+        return Some(Violation::at(
+            start,
+            period,
+            0,
+            ViolationKind::InvalidPeriod,
+        ));
+    }
+    if shadow.len() != elevation.len() {
+        let index = if shadow.len() < elevation.len() {
+            shadow.len()
+        } else {
+            elevation.len()
+        };
+        let kind = ViolationKind::LengthMismatch {
+            shadow: shadow.len(),
+            elevation: elevation.len(),
+        };
+        return Some(Violation::at(start, period, index, kind));
+    }
+
+    let mut i = 0;
+    while i < shadow.len() {
+        let (prev_shadow, prev_elevation) = match i {
+            0 => (None, None),
+            _ => (Some(shadow[i - 1] as i32), Some(elevation[i - 1] as i32)),
+        };
+        if let Some(kind) = check_shadow(prev_shadow, shadow[i] as i32, period) {
+            return Some(Violation::at(start, period, i, kind));
+        }
+        if let Some(kind) = check_elevation(prev_elevation, elevation[i] as i32, period) {
+            return Some(Violation::at(start, period, i, kind));
+        }
+        i += 1;
+    }
+    None
+}
@@ -0,0 +1,168 @@
+use core::ops::Range;
+
+use crate::defs::{Channel, Ephemeris};
+use crate::error::EphemerisError;
+use crate::interpolation::Interpolation;
+use crate::units::{Centidegrees, Decidegrees, Degrees};
+use crate::{elevation_from_unix_timestamp_with, shadow_angle_from_unix_timestamp_with};
+
+/// Iterator over interpolated values, see `Ephemeris::samples`
+pub struct Samples<'a, E: ?Sized> {
+    data: &'a E,
+    next: i64,
+    end: i64,
+    step: i64,
+    interpolation: Interpolation,
+    done: bool,
+}
+
+impl<'a, E: Ephemeris + ?Sized> Samples<'a, E> {
+    pub(crate) fn new(data: &'a E, range: Range<i64>, step: u32) -> Self {
+        Samples {
+            data,
+            next: range.start,
+            end: range.end,
+            step: step as i64,
+            interpolation: Interpolation::default(),
+            // a zero step would never leave the range start
+            done: step == 0,
+        }
+    }
+
+    /// Use provided interpolation scheme instead of the linear one
+    pub fn interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+}
+
+impl<E: Ephemeris + ?Sized> Iterator for Samples<'_, E> {
+    /// timestamp, shadow angle and elevation
+    type Item = Result<(i64, Centidegrees, Decidegrees), EphemerisError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.next >= self.end {
+            return None;
+        }
+        let unix = self.next;
+        self.next = unix.saturating_add(self.step);
+
+        let sample = shadow_angle_from_unix_timestamp_with(self.data, unix, self.interpolation)
+            .and_then(|shadow| {
+                elevation_from_unix_timestamp_with(self.data, unix, self.interpolation)
+                    .map(|elevation| (unix, shadow, elevation))
+            });
+        // a failed lookup ends the iteration
+        self.done = sample.is_err();
+
+        Some(sample)
+    }
+}
+
+/// Iterator over stored entries, see `Ephemeris::entries`
+pub struct Entries<'a, E: ?Sized> {
+    data: &'a E,
+    index: usize,
+    len: usize,
+}
+
+impl<'a, E: Ephemeris + ?Sized> Entries<'a, E> {
+    pub(crate) fn new(data: &'a E) -> Self {
+        Entries {
+            data,
+            index: 0,
+            len: data.shadow().len().min(data.elevation().len()),
+        }
+    }
+}
+
+impl<E: Ephemeris + ?Sized> Iterator for Entries<'_, E> {
+    /// timestamp, stored shadow angle and elevation
+    type Item = (i64, Decidegrees, Degrees);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.len {
+            return None;
+        }
+        let i = self.index;
+        self.index += 1;
+
+        let unix = self.data.start() as i64 + self.data.period() as i64 * i as i64;
+        let shadow = Decidegrees(self.data.shadow().sample(i)?);
+        let elevation = Degrees(self.data.elevation().sample(i)?);
+        Some((unix, shadow, elevation))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.len - self.index;
+        (n, Some(n))
+    }
+}
+
+impl<E: Ephemeris + ?Sized> ExactSizeIterator for Entries<'_, E> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Centidegrees as C, Decidegrees as D, Degrees};
+    use crate::{Ephemeris, EphemerisError, Interpolation, MoonEphemeris};
+
+    const EPHEMERIS: MoonEphemeris = MoonEphemeris {
+        start: 1000,
+        period: 3600,
+        shadow: &[0, 900, 1800, 2700],
+        elevation: &[0, 90, 0, -90, 0],
+    };
+
+    #[test]
+    fn interpolated_samples() {
+        let samples: Vec<_> = EPHEMERIS.samples(1000..1000 + 3 * 3600, 1800).collect();
+        assert_eq!(samples.len(), 6);
+        assert_eq!(samples[0], Ok((1000, C(0), D(0))));
+        assert_eq!(samples[1], Ok((2800, C(4500), D(450))));
+        assert_eq!(samples[5], Ok((1000 + 5 * 1800, C(22500), D(-450))));
+
+        let cubic: Vec<_> = EPHEMERIS
+            .samples(1000..1000 + 3 * 3600, 1800)
+            .interpolation(Interpolation::CubicHermite)
+            .collect();
+        assert_eq!(cubic.len(), 6);
+        assert_ne!(cubic, samples);
+    }
+
+    #[test]
+    fn zero_step_yields_nothing() {
+        assert_eq!(EPHEMERIS.samples(1000..1000 + 3 * 3600, 0).count(), 0);
+    }
+
+    #[test]
+    fn samples_stop_on_error() {
+        let samples: Vec<_> = EPHEMERIS.samples(1000..1000 + 10 * 3600, 3600).collect();
+        assert_eq!(samples.len(), 5);
+        assert_eq!(
+            samples[4],
+            Err(EphemerisError::AfterEnd {
+                last_valid: 1000 + 3 * 3600
+            })
+        );
+
+        let mut before = EPHEMERIS.samples(0..2000, 100);
+        assert_eq!(before.next(), Some(Err(EphemerisError::BeforeStart)));
+        assert_eq!(before.next(), None);
+    }
+
+    #[test]
+    fn raw_entries() {
+        let entries = EPHEMERIS.entries();
+        // only entries holding both channels are listed
+        assert_eq!(entries.len(), 4);
+        assert_eq!(
+            entries.collect::<Vec<_>>(),
+            [
+                (1000, D(0), Degrees(0)),
+                (4600, D(900), Degrees(90)),
+                (8200, D(1800), Degrees(0)),
+                (11800, D(2700), Degrees(-90))
+            ]
+        );
+    }
+}
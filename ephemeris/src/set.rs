@@ -0,0 +1,425 @@
+use crate::compact::SHADOW_MODULUS;
+use crate::coverage::ChannelCoverage;
+use crate::defs::{Channel, Ephemeris, MoonEphemeris};
+use crate::error::EphemerisError;
+use crate::interpolation::Interpolation;
+use crate::{approx_angle, modulo_half_half};
+
+/// Several ephemeris segments looked up as a single one
+///
+/// Segments are expected sorted by start and may overlap or leave a gap of
+/// at most one period between them. Where segments overlap, lookups use the
+/// one with the shortest period, the latest one in the list winning ties.
+/// Where that segment starts or ends inside another one, values blend from
+/// the other segment to it over one period of the other segment, so they do
+/// not jump at the boundary. Between two consecutive segments values are
+/// linearly interpolated from the last sample of the first one to the first
+/// sample of the next one.
+///
+/// The set is an `Ephemeris` whose channels are resampled every shortest
+/// segment period from the earliest segment start, in stored units, so it can
+/// be used wherever a single table is. Resampled values are exact on the grid
+/// of segments sharing that period and start alignment.
+#[derive(Debug, Clone, Copy)]
+pub struct EphemerisSet<'a, E = MoonEphemeris> {
+    segments: &'a [E],
+    shadow: SetChannel<'a, E>,
+    elevation: SetChannel<'a, E>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Shadow,
+    Elevation,
+}
+
+/// Channel of an `EphemerisSet`, resampled from its segments
+#[derive(Debug, Clone, Copy)]
+pub struct SetChannel<'a, E> {
+    segments: &'a [E],
+    kind: Kind,
+}
+
+impl<'a, E> EphemerisSet<'a, E> {
+    pub const fn new(segments: &'a [E]) -> Self {
+        EphemerisSet {
+            segments,
+            shadow: SetChannel {
+                segments,
+                kind: Kind::Shadow,
+            },
+            elevation: SetChannel {
+                segments,
+                kind: Kind::Elevation,
+            },
+        }
+    }
+
+    pub fn segments(&self) -> &'a [E] {
+        self.segments
+    }
+}
+
+impl<'a, E: Ephemeris> Ephemeris for EphemerisSet<'a, E> {
+    type Shadow = SetChannel<'a, E>;
+    type Elevation = SetChannel<'a, E>;
+
+    /// Earliest segment start
+    fn start(&self) -> u64 {
+        grid_start(self.segments)
+    }
+
+    /// Shortest segment period
+    fn period(&self) -> u32 {
+        grid_period(self.segments)
+    }
+
+    fn shadow(&self) -> &SetChannel<'a, E> {
+        &self.shadow
+    }
+
+    fn elevation(&self) -> &SetChannel<'a, E> {
+        &self.elevation
+    }
+}
+
+fn grid_start<E: Ephemeris>(segments: &[E]) -> u64 {
+    segments.iter().map(|s| s.start()).min().unwrap_or(0)
+}
+
+fn grid_period<E: Ephemeris>(segments: &[E]) -> u32 {
+    // a set without segments has empty channels, any period reports it
+    segments
+        .iter()
+        .map(|s| s.period())
+        .filter(|&p| p != 0)
+        .min()
+        .unwrap_or(1)
+}
+
+impl<E: Ephemeris> Channel for SetChannel<'_, E> {
+    fn len(&self) -> usize {
+        let start = grid_start(self.segments) as i64;
+        let period = grid_period(self.segments) as i64;
+        self.segments
+            .iter()
+            .filter_map(|s| self.coverage(s))
+            .map(|c| c.last)
+            .max()
+            .map_or(0, |last| ((last - start) / period + 1) as usize)
+    }
+
+    fn sample(&self, index: usize) -> Option<i32> {
+        if index >= self.len() {
+            return None;
+        }
+        let period = grid_period(self.segments) as i64;
+        let unix = (grid_start(self.segments) as i64).checked_add(period * index as i64)?;
+
+        // back from 10x stored unit, rounded to the closest
+        let value = (self.lookup(unix, Interpolation::Linear).ok()? + 5).div_euclid(10);
+        Some(match self.kind {
+            Kind::Shadow => value.rem_euclid(SHADOW_MODULUS as i32),
+            Kind::Elevation => value,
+        })
+    }
+}
+
+impl<E: Ephemeris> SetChannel<'_, E> {
+    fn coverage(&self, s: &E) -> Option<ChannelCoverage> {
+        match self.kind {
+            Kind::Shadow => ChannelCoverage::of(s, s.shadow()),
+            Kind::Elevation => ChannelCoverage::of(s, s.elevation()),
+        }
+    }
+
+    /// Angle of a segment in 10x stored unit, shadow angles are not wrapped
+    fn value(&self, s: &E, unix: i64, interpolation: Interpolation) -> Result<i32, EphemerisError> {
+        match self.kind {
+            Kind::Shadow => approx_angle(s, s.shadow(), unix, interpolation),
+            Kind::Elevation => approx_angle(s, s.elevation(), unix, interpolation),
+        }
+    }
+
+    /// Bring `b` next to `a` on wrapping channels
+    fn unwrap_near(&self, a: i32, b: i32) -> i32 {
+        match self.kind {
+            Kind::Shadow => a + modulo_half_half(b - a),
+            Kind::Elevation => b,
+        }
+    }
+
+    /// Angle at provided timestamp in 10x stored unit, shadow angles are not
+    /// wrapped
+    fn lookup(&self, unix: i64, interpolation: Interpolation) -> Result<i32, EphemerisError> {
+        let sample = |s: &E, index: usize| match self.kind {
+            Kind::Shadow => s.shadow().sample(index),
+            Kind::Elevation => s.elevation().sample(index),
+        };
+
+        // two finest segments holding timestamp, and closest ones on each side
+        let mut best: Option<(&E, ChannelCoverage)> = None;
+        let mut other: Option<(&E, ChannelCoverage)> = None;
+        let mut prev: Option<(&E, ChannelCoverage)> = None;
+        let mut next: Option<(&E, ChannelCoverage)> = None;
+        for s in self.segments {
+            let Some(c) = self.coverage(s) else {
+                continue;
+            };
+            if c.contains(unix) {
+                if best.map_or(true, |(b, _)| s.period() <= b.period()) {
+                    other = best;
+                    best = Some((s, c));
+                } else if other.map_or(true, |(o, _)| s.period() <= o.period()) {
+                    other = Some((s, c));
+                }
+            } else if c.last < unix {
+                match prev {
+                    Some((_, p)) if p.last > c.last => {}
+                    _ => prev = Some((s, c)),
+                }
+            } else {
+                match next {
+                    Some((_, n)) if n.first <= c.first => {}
+                    _ => next = Some((s, c)),
+                }
+            }
+        }
+
+        if let Some((b, bc)) = best {
+            let bv = self.value(b, unix, interpolation)?;
+            let Some((o, oc)) = other else {
+                return Ok(bv);
+            };
+
+            let (x, len) = blend_weight(bc, oc, o.period() as i64, unix);
+            if x == len {
+                return Ok(bv);
+            }
+            let ov = self.value(o, unix, interpolation)?;
+            let bv = self.unwrap_near(ov, bv);
+            return Ok(Interpolation::Linear.interpolate([ov, ov, bv, bv], x as i32, len as i32));
+        }
+
+        match (prev, next) {
+            (Some((p, pc)), Some((n, nc))) => {
+                // only bridge gaps a single table could have had
+                let gap = nc.first - pc.last;
+                if gap > p.period().max(n.period()) as i64 {
+                    return Err(EphemerisError::AfterEnd {
+                        last_valid: pc.last,
+                    });
+                }
+
+                let pa = 10 * sample(p, pc.len - 1).ok_or(EphemerisError::EmptyTable)?;
+                let na = 10 * sample(n, 0).ok_or(EphemerisError::EmptyTable)?;
+                let na = self.unwrap_near(pa, na);
+                Ok(Interpolation::Linear.interpolate(
+                    [pa, pa, na, na],
+                    (unix - pc.last) as i32,
+                    gap as i32,
+                ))
+            }
+            (Some((_, pc)), None) => Err(EphemerisError::AfterEnd {
+                last_valid: pc.last,
+            }),
+            (None, Some(_)) => Err(EphemerisError::BeforeStart),
+            (None, None) => Err(EphemerisError::EmptyTable),
+        }
+    }
+}
+
+/// Weight of segment `inner` against overlapping segment `outer` at provided
+/// timestamp, as a fraction `(x, len)`
+///
+/// Weight ramps up from zero where `inner` starts inside `outer` and down to
+/// zero where it ends inside it, over at most `ramp` seconds and always
+/// within the overlap.
+fn blend_weight(
+    inner: ChannelCoverage,
+    outer: ChannelCoverage,
+    ramp: i64,
+    unix: i64,
+) -> (i64, i64) {
+    let mut weight = (1, 1);
+    // x seconds into a ramp of len seconds, keep the lowest weight
+    let mut limit = |x: i64, len: i64| {
+        let len = len.min(ramp);
+        if len > 0 && x < len && x * weight.1 < weight.0 * len {
+            weight = (x, len);
+        }
+    };
+    if inner.first > outer.first {
+        limit(unix - inner.first, outer.last - inner.first);
+    }
+    if inner.last < outer.last {
+        limit(inner.last - unix, inner.last - outer.first);
+    }
+    weight
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::{Centidegrees, Decidegrees};
+    use crate::{
+        elevation_from_unix_timestamp, moon_state_at, next_phase_event,
+        shadow_angle_from_unix_timestamp,
+    };
+
+    // hourly table followed by a quarter hour one, overlapping its last hour
+    const SEGMENTS: [MoonEphemeris; 2] = [
+        MoonEphemeris {
+            start: 1000,
+            period: 3600,
+            shadow: &[3590, 3500, 3400, 3300, 3200],
+            elevation: &[0, 10, 20, 30, 40],
+        },
+        MoonEphemeris {
+            start: 1000 + 3 * 3600,
+            period: 900,
+            shadow: &[3310, 3280, 3250, 3220, 3190, 3160, 3130, 3100, 3070],
+            elevation: &[31, 32, 33, 34, 35, 36, 37, 38, 39],
+        },
+    ];
+    const SET: EphemerisSet = EphemerisSet::new(&SEGMENTS);
+
+    #[test]
+    fn dispatch_to_finest_segment() {
+        assert_eq!(SET.start(), 1000);
+        assert_eq!(SET.period(), 900);
+        assert_eq!(SET.end(), Some(1000 + 5 * 3600));
+
+        assert_eq!(
+            shadow_angle_from_unix_timestamp(&SET, 1000 + 1800),
+            shadow_angle_from_unix_timestamp(&SEGMENTS[0], 1000 + 1800)
+        );
+        // once the hourly segment ends, the quarter hour one is used as is
+        assert_eq!(
+            shadow_angle_from_unix_timestamp(&SET, 1000 + 4 * 3600 + 900),
+            Ok(Centidegrees(31600))
+        );
+        assert_eq!(
+            elevation_from_unix_timestamp(&SET, 1000 + 4 * 3600 + 450),
+            Ok(Decidegrees(355))
+        );
+    }
+
+    #[test]
+    fn blend_across_overlap() {
+        let shadow = |t: i64| shadow_angle_from_unix_timestamp(&SET, 1000 + t).unwrap().0;
+
+        // overlap starts on the hourly segment and ends on the quarter hour one
+        assert_eq!(shadow(3 * 3600), 33000);
+        assert_eq!(shadow(4 * 3600), 31900);
+        // a quarter of the way, hourly 327.5 and quarter hour 328 degrees
+        assert_eq!(shadow(3 * 3600 + 900), 32760);
+
+        // no jump anywhere, both segments move about 0.3 degree a minute
+        for t in (0..5 * 3600).step_by(60) {
+            assert!((shadow(t + 60) - shadow(t)).abs() <= 30, "{t}");
+        }
+    }
+
+    #[test]
+    fn interpolate_across_segments() {
+        const SPLIT: [MoonEphemeris; 2] = [
+            MoonEphemeris {
+                start: 1000,
+                period: 3600,
+                shadow: &[20, 10],
+                elevation: &[0, 10],
+            },
+            MoonEphemeris {
+                start: 1000 + 2 * 3600,
+                period: 3600,
+                shadow: &[3590, 3580],
+                elevation: &[20, 30],
+            },
+        ];
+        let set = EphemerisSet::new(&SPLIT);
+
+        // wrapping shadow angle through the boundary between segments
+        assert_eq!(
+            shadow_angle_from_unix_timestamp(&set, 1000 + 3600 + 1800),
+            Ok(Centidegrees(0))
+        );
+        assert_eq!(
+            elevation_from_unix_timestamp(&set, 1000 + 3600 + 1800),
+            Ok(Decidegrees(150))
+        );
+        assert_eq!(
+            shadow_angle_from_unix_timestamp(&set, 1000 + 2 * 3600),
+            Ok(Centidegrees(35900))
+        );
+    }
+
+    #[test]
+    fn lookup_errors() {
+        assert_eq!(
+            shadow_angle_from_unix_timestamp(&SET, 999),
+            Err(EphemerisError::BeforeStart)
+        );
+        assert_eq!(
+            elevation_from_unix_timestamp(&SET, 1001 + 5 * 3600),
+            Err(EphemerisError::AfterEnd {
+                last_valid: 1000 + 5 * 3600
+            })
+        );
+
+        // segments too far apart leave a hole
+        const HOLE: [MoonEphemeris; 2] = [
+            MoonEphemeris {
+                start: 1000,
+                period: 3600,
+                shadow: &[0, 0],
+                elevation: &[0, 0],
+            },
+            MoonEphemeris {
+                start: 1000 + 10 * 3600,
+                period: 3600,
+                shadow: &[0, 0],
+                elevation: &[0, 0],
+            },
+        ];
+        assert!(
+            shadow_angle_from_unix_timestamp(&EphemerisSet::new(&HOLE), 1000 + 5 * 3600).is_err()
+        );
+
+        let empty: EphemerisSet = EphemerisSet::new(&[]);
+        assert_eq!(
+            shadow_angle_from_unix_timestamp(&empty, 1000),
+            Err(EphemerisError::EmptyTable)
+        );
+    }
+
+    #[test]
+    fn chained_tables_serve_every_lookup() {
+        // bundled table split in two segments overlapping by a day
+        let data = &crate::MOON_EPHEMERIS;
+        let split = 400 * 24;
+        let overlap = 24;
+        let (shadow, elevation) = (data.shadow, data.elevation);
+        let segments = [
+            MoonEphemeris {
+                start: data.start,
+                period: data.period,
+                shadow: &shadow[..split + overlap],
+                elevation: &elevation[..split + overlap],
+            },
+            MoonEphemeris {
+                start: data.start + (split * data.period as usize) as u64,
+                period: data.period,
+                shadow: &shadow[split..],
+                elevation: &elevation[split..],
+            },
+        ];
+        let set = EphemerisSet::new(&segments);
+        assert_eq!(set.end(), data.end());
+
+        // same samples on both sides, the set matches the single table
+        let t = data.start as i64 + (split as i64 + 3) * data.period as i64 + 1234;
+        assert_eq!(moon_state_at(&set, t), moon_state_at(data, t));
+        assert_eq!(next_phase_event(&set, t), next_phase_event(data, t));
+    }
+}
@@ -0,0 +1,38 @@
+use libm::{cos, sin};
+
+use crate::lunar::{normalize_degrees, EclipticPosition};
+use crate::time::centuries;
+
+/// Astronomical unit in kilometers
+pub const AU_KM: f64 = 149_597_870.7;
+
+/// Geometric geocentric position of the Sun in the mean ecliptic of date
+///
+/// Low accuracy solar theory (Meeus, Astronomical Algorithms, chapter 25),
+/// good to about 0.01 degree.
+pub fn sun_position(unix: i64) -> EclipticPosition {
+    let t = centuries(unix);
+
+    // geometric mean longitude and mean anomaly
+    let l0 = 280.46646 + 36000.76983 * t + 0.0003032 * t * t;
+    let m = (357.52911 + 35999.05029 * t - 0.0001537 * t * t).to_radians();
+    // earth orbit eccentricity
+    let e = 0.016708634 - 0.000042037 * t - 0.0000001267 * t * t;
+
+    // equation of center
+    let c = (1.914602 - 0.004817 * t - 0.000014 * t * t) * sin(m)
+        + (0.019993 - 0.000101 * t) * sin(2.0 * m)
+        + 0.000289 * sin(3.0 * m);
+
+    // true longitude and anomaly
+    let longitude = l0 + c;
+    let v = m + c.to_radians();
+
+    let r = 1.000001018 * (1.0 - e * e) / (1.0 + e * cos(v));
+
+    EclipticPosition {
+        longitude: normalize_degrees(longitude),
+        latitude: 0.0,
+        distance: r * AU_KM,
+    }
+}
@@ -0,0 +1,138 @@
+use libm::{atan2, cos, round, sin};
+
+use crate::defs::Ephemeris;
+use crate::error::EphemerisError;
+use crate::lunar::{moon_position, normalize_degrees};
+use crate::shadow_angle_from_unix_timestamp;
+use crate::solar::sun_position;
+use crate::units::Centidegrees;
+
+/// Illuminated fraction of a fully lit moon disc
+pub const ILLUMINATION_ONE: u16 = 10000;
+/// Mean duration between two new moons in days
+pub const SYNODIC_MONTH_DAYS: f64 = 29.530589;
+
+/// Moon appearance at a given time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoonState {
+    // shadow angle ranging [0;360[
+    pub shadow: Centidegrees,
+    // illuminated fraction of the disc ranging [0;ILLUMINATION_ONE]
+    pub illumination: u16,
+    // true from new moon to full moon
+    pub waxing: bool,
+    // time elapsed since new moon in hundredths of day
+    pub age: u16,
+    // position angle of the bright limb midpoint ranging [0;360[, measured
+    // from celestial north towards east
+    pub bright_limb: Centidegrees,
+}
+
+impl MoonState {
+    /// Derive moon state from its shadow angle at provided timestamp
+    fn new(shadow: Centidegrees, unix: i64) -> Self {
+        // shadow angle is the supplement of the Sun-Moon-Earth phase angle
+        let psi = shadow.degrees().to_radians();
+        let illumination = round(ILLUMINATION_ONE as f64 * (1.0 - cos(psi)) / 2.0) as u16;
+
+        // shadow angle decreases from 360 degrees down to 0 over a lunation
+        let elapsed = (36000 - shadow.0) % 36000;
+        let age = round(100.0 * SYNODIC_MONTH_DAYS * elapsed as f64 / 36000.0) as u16;
+
+        let bright_limb = Centidegrees(round(100.0 * bright_limb_angle(unix)) as i32 % 36000);
+
+        MoonState {
+            shadow,
+            illumination,
+            waxing: shadow > Centidegrees(18000),
+            age,
+            bright_limb,
+        }
+    }
+}
+
+/// Position angle of the bright limb midpoint in degrees ranging [0;360[,
+/// measured from celestial north towards east (Meeus 48.5)
+pub(crate) fn bright_limb_angle(unix: i64) -> f64 {
+    let (a, d) = moon_position(unix).equatorial(unix);
+    let (a0, d0) = sun_position(unix).equatorial(unix);
+    let (a, d, a0, d0) = (
+        a.to_radians(),
+        d.to_radians(),
+        a0.to_radians(),
+        d0.to_radians(),
+    );
+    let chi = atan2(
+        cos(d0) * sin(a0 - a),
+        sin(d0) * cos(d) - cos(d0) * sin(d) * cos(a0 - a),
+    );
+    normalize_degrees(chi.to_degrees())
+}
+
+/// Return moon state at provided timestamp
+///
+/// Illumination, waxing flag and age derive from the table shadow angle, age
+/// assumes a mean synodic month and may be off by about half a day. Bright
+/// limb orientation needs both bodies positions and comes from the analytic
+/// model.
+pub fn moon_state_at<E: Ephemeris + ?Sized>(
+    data: &E,
+    unix: i64,
+) -> Result<MoonState, EphemerisError> {
+    let shadow = shadow_angle_from_unix_timestamp(data, unix)?;
+    Ok(MoonState::new(shadow, unix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analytic_shadow_angle_from_unix_timestamp, phase_events, PhaseEvent};
+    use crate::{MoonEphemeris, MOON_EPHEMERIS};
+
+    #[test]
+    fn meeus_example() {
+        // Meeus example 48.a, 1992 April 12 0h TD
+        let unix = 703_036_741;
+        let state = MoonState::new(analytic_shadow_angle_from_unix_timestamp(unix), unix);
+
+        assert!(state.illumination.abs_diff(6786) <= 10);
+        assert!(state.waxing);
+        assert!(state.bright_limb.0.abs_diff(28500) <= 20);
+    }
+
+    #[test]
+    fn state_over_a_lunation() {
+        let start = MOON_EPHEMERIS.start as i64;
+        let (_, new) = phase_events(&MOON_EPHEMERIS, start..start + 40 * 86400)
+            .map(Result::unwrap)
+            .find(|&(e, _)| e == PhaseEvent::NewMoon)
+            .unwrap();
+
+        let day = |d: i64| moon_state_at(&MOON_EPHEMERIS, new + d * 86400).unwrap();
+        assert!(day(1).illumination < 500 && day(1).waxing);
+        assert!(day(7).illumination.abs_diff(ILLUMINATION_ONE / 2) < 1500);
+        assert!(day(15).illumination > 9500);
+        assert!(!day(22).waxing);
+        // age grows by a day each day
+        for d in 1..28 {
+            assert!(day(d).age.abs_diff(100 * d as u16) < 100);
+        }
+        // bright limb faces west when waxing and east when waning
+        assert!(day(4).bright_limb > Centidegrees(18000));
+        assert!(day(25).bright_limb < Centidegrees(18000));
+    }
+
+    #[test]
+    fn lookup_errors_are_forwarded() {
+        const EPHEMERIS: MoonEphemeris = MoonEphemeris {
+            start: 1000,
+            period: 10,
+            shadow: &[],
+            elevation: &[],
+        };
+        assert_eq!(
+            moon_state_at(&EPHEMERIS, 1000),
+            Err(EphemerisError::EmptyTable)
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Time scales used by the analytic models
+//!
+//! Timestamps are unix ones, counting UTC seconds and ignoring leap seconds.
+//! Terrestrial time (TT) drives the lunar and solar theories while Earth
+//! rotation follows universal time, taken equal to UTC.
+
+/// Julian day of unix epoch
+pub const UNIX_EPOCH_JD: f64 = 2_440_587.5;
+/// Julian day of J2000.0 epoch
+pub const J2000_JD: f64 = 2_451_545.0;
+/// Offset between Julian days and modified Julian days
+pub const MJD_OFFSET: f64 = 2_400_000.5;
+/// Offset between terrestrial time and international atomic time in seconds
+pub const TT_MINUS_TAI_S: f64 = 32.184;
+
+/// Unix timestamps TAI-UTC changed at, with its new value in seconds
+const LEAP_SECONDS: [(i64, i32); 28] = [
+    (63072000, 10),   // 1972-01-01
+    (78796800, 11),   // 1972-07-01
+    (94694400, 12),   // 1973-01-01
+    (126230400, 13),  // 1974-01-01
+    (157766400, 14),  // 1975-01-01
+    (189302400, 15),  // 1976-01-01
+    (220924800, 16),  // 1977-01-01
+    (252460800, 17),  // 1978-01-01
+    (283996800, 18),  // 1979-01-01
+    (315532800, 19),  // 1980-01-01
+    (362793600, 20),  // 1981-07-01
+    (394329600, 21),  // 1982-07-01
+    (425865600, 22),  // 1983-07-01
+    (489024000, 23),  // 1985-07-01
+    (567993600, 24),  // 1988-01-01
+    (631152000, 25),  // 1990-01-01
+    (662688000, 26),  // 1991-01-01
+    (709948800, 27),  // 1992-07-01
+    (741484800, 28),  // 1993-07-01
+    (773020800, 29),  // 1994-07-01
+    (820454400, 30),  // 1996-01-01
+    (867715200, 31),  // 1997-07-01
+    (915148800, 32),  // 1999-01-01
+    (1136073600, 33), // 2006-01-01
+    (1230768000, 34), // 2009-01-01
+    (1341100800, 35), // 2012-07-01
+    (1435708800, 36), // 2015-07-01
+    (1483228800, 37), // 2017-01-01
+];
+
+/// Instant expressed as a Julian day in UTC
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct JulianDay(pub f64);
+
+impl JulianDay {
+    pub fn from_unix(unix: i64) -> Self {
+        JulianDay(UNIX_EPOCH_JD + unix as f64 / 86400.0)
+    }
+
+    /// Unix timestamp rounded to the closest second
+    pub fn to_unix(self) -> i64 {
+        libm::round((self.0 - UNIX_EPOCH_JD) * 86400.0) as i64
+    }
+
+    pub fn from_modified(mjd: f64) -> Self {
+        JulianDay(mjd + MJD_OFFSET)
+    }
+
+    /// Modified Julian day
+    pub fn modified(self) -> f64 {
+        self.0 - MJD_OFFSET
+    }
+}
+
+/// Julian day of provided unix timestamp
+pub fn julian_day(unix: i64) -> f64 {
+    JulianDay::from_unix(unix).0
+}
+
+/// Offset between international atomic time and UTC in seconds, None
+/// before leap seconds were introduced in 1972
+///
+/// Leap seconds announced after the last table entry are not known.
+pub fn tai_minus_utc(unix: i64) -> Option<i32> {
+    LEAP_SECONDS
+        .iter()
+        .rev()
+        .find(|&&(t, _)| t <= unix)
+        .map(|&(_, s)| s)
+}
+
+/// Offset between terrestrial time and UTC in seconds
+///
+/// Follows the leap second table from 1972 on, and the ΔT model before.
+pub fn tt_minus_utc(unix: i64) -> f64 {
+    match tai_minus_utc(unix) {
+        Some(s) => TT_MINUS_TAI_S + s as f64,
+        None => delta_t(unix),
+    }
+}
+
+/// Offset ΔT between terrestrial time and universal time in seconds
+///
+/// UTC stays within a second of universal time from 1972 on, before that
+/// the polynomial expressions of Espenak and Meeus are used.
+pub fn delta_t(unix: i64) -> f64 {
+    if tai_minus_utc(unix).is_some() {
+        return tt_minus_utc(unix);
+    }
+
+    // decimal year
+    let y = 2000.0 + (julian_day(unix) - J2000_JD) / 365.25;
+    let poly = |t: f64, c: &[f64]| c.iter().rev().fold(0.0, |acc, &c| acc * t + c);
+    match y {
+        y if y < 1800.0 => {
+            let u = (y - 1820.0) / 100.0;
+            -20.0 + 32.0 * u * u
+        }
+        y if y < 1860.0 => poly(
+            y - 1800.0,
+            &[
+                13.72,
+                -0.332447,
+                0.0068612,
+                0.0041116,
+                -0.00037436,
+                0.0000121272,
+                -0.0000001699,
+                0.000000000875,
+            ],
+        ),
+        y if y < 1900.0 => poly(
+            y - 1860.0,
+            &[
+                7.62,
+                0.5737,
+                -0.251754,
+                0.01680668,
+                -0.0004473624,
+                1.0 / 233174.0,
+            ],
+        ),
+        y if y < 1920.0 => poly(
+            y - 1900.0,
+            &[-2.79, 1.494119, -0.0598939, 0.0061966, -0.000197],
+        ),
+        y if y < 1941.0 => poly(y - 1920.0, &[21.20, 0.84493, -0.076100, 0.0020936]),
+        y if y < 1961.0 => poly(y - 1950.0, &[29.07, 0.407, -1.0 / 233.0, 1.0 / 2547.0]),
+        y => poly(y - 1975.0, &[45.45, 1.067, -1.0 / 260.0, -1.0 / 718.0]),
+    }
+}
+
+/// Julian day in terrestrial time of provided unix timestamp
+pub fn julian_day_tt(unix: i64) -> f64 {
+    julian_day(unix) + tt_minus_utc(unix) / 86400.0
+}
+
+/// Julian centuries of terrestrial time elapsed since J2000.0
+pub(crate) fn centuries(unix: i64) -> f64 {
+    (julian_day_tt(unix) - J2000_JD) / 36525.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn julian_days() {
+        // Meeus example 7.a, 1957 October 4.81
+        let sputnik = JulianDay(2_436_116.31);
+        assert_eq!(sputnik.to_unix(), -386_310_816);
+        assert!((JulianDay::from_unix(-386_310_816).0 - 2_436_116.31).abs() < 1e-6);
+
+        assert_eq!(julian_day(0), UNIX_EPOCH_JD);
+        assert_eq!(JulianDay::from_unix(946_728_000).0, J2000_JD);
+        assert_eq!(JulianDay::from_unix(0).modified(), 40587.0);
+        assert_eq!(JulianDay::from_modified(51544.5).0, J2000_JD);
+    }
+
+    #[test]
+    fn leap_seconds() {
+        assert_eq!(tai_minus_utc(63_071_999), None);
+        assert_eq!(tai_minus_utc(63_072_000), Some(10));
+        assert_eq!(tai_minus_utc(1_483_228_799), Some(36));
+        assert_eq!(tai_minus_utc(1_762_879_028), Some(37));
+
+        assert_eq!(tt_minus_utc(1_762_879_028), 69.184);
+        // Meeus example 10.a, 1977 February 18
+        assert_eq!(tt_minus_utc(224_035_200), 48.184);
+    }
+
+    #[test]
+    fn delta_t_model() {
+        // model joins the leap second table smoothly
+        let before = delta_t(63_071_999);
+        assert!((before - 42.184).abs() < 0.5, "{before}");
+
+        // published values, Meeus table 10.A
+        for (year, expected) in [(1900, -2.8), (1920, 21.2), (1950, 29.1)] {
+            let unix = ((year - 1970) as f64 * 365.25 * 86400.0) as i64;
+            assert!((delta_t(unix) - expected).abs() < 1.0, "{year}");
+        }
+        // Earth rotation lagged about two hours behind by year 333
+        let unix = JulianDay(1_842_713.0).to_unix();
+        assert!((delta_t(unix) - 7200.0).abs() < 400.0, "{}", delta_t(unix));
+    }
+}
@@ -0,0 +1,103 @@
+use libm::round;
+
+use crate::observer::Observer;
+use crate::solar::sun_position;
+use crate::units::{Centidegrees, Decidegrees};
+
+/// Sun center elevation in degrees at sunrise and sunset, accounting for
+/// standard refraction and the sun semidiameter
+pub const SUNRISE_ELEVATION: Centidegrees = Centidegrees(-83);
+
+/// Sky brightness periods, ordered from brightest to darkest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Twilight {
+    /// sun upper limb above horizon
+    Day,
+    /// sun center down to 6 degrees below horizon
+    Civil,
+    /// sun center down to 12 degrees below horizon
+    Nautical,
+    /// sun center down to 18 degrees below horizon
+    Astronomical,
+    /// sun center more than 18 degrees below horizon
+    Night,
+}
+
+impl Twilight {
+    /// Classify provided sun center elevation
+    pub fn from_sun_elevation(elevation: Centidegrees) -> Self {
+        if elevation > SUNRISE_ELEVATION {
+            Twilight::Day
+        } else if elevation > Centidegrees(-600) {
+            Twilight::Civil
+        } else if elevation > Centidegrees(-1200) {
+            Twilight::Nautical
+        } else if elevation > Centidegrees(-1800) {
+            Twilight::Astronomical
+        } else {
+            Twilight::Night
+        }
+    }
+}
+
+impl Observer {
+    /// Sky brightness period seen by observer at provided timestamp
+    pub fn twilight_at(&self, unix: i64) -> Twilight {
+        let h = self.horizontal(&sun_position(unix), unix);
+        Twilight::from_sun_elevation(h.elevation)
+    }
+}
+
+/// Return sun topocentric elevation angle ranging [-90,90]
+/// seen by provided observer at provided timestamp
+///
+/// Elevation is geometric, no atmospheric refraction is applied.
+pub fn sun_elevation_from_unix_timestamp(observer: &Observer, unix: i64) -> Decidegrees {
+    let (_, elevation) = observer.horizontal_degrees(&sun_position(unix), unix);
+    Decidegrees(round(10.0 * elevation) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GREENWICH: Observer = Observer {
+        latitude: 51.48,
+        longitude: 0.0,
+        height: 0.0,
+    };
+
+    #[test]
+    fn elevation_thresholds() {
+        let twilight = |centidegrees| Twilight::from_sun_elevation(Centidegrees(centidegrees));
+        assert_eq!(twilight(3000), Twilight::Day);
+        assert_eq!(twilight(-50), Twilight::Day);
+        assert_eq!(twilight(-100), Twilight::Civil);
+        assert_eq!(twilight(-800), Twilight::Nautical);
+        assert_eq!(twilight(-1500), Twilight::Astronomical);
+        assert_eq!(twilight(-4000), Twilight::Night);
+        assert!(Twilight::Day < Twilight::Night);
+    }
+
+    #[test]
+    fn greenwich_summer_solstice() {
+        // 2025-06-21 00:00 UTC, sun rises at 03:43 and sets at 20:21
+        let midnight = 1_750_464_000;
+        let at = |h: i64, m: i64| GREENWICH.twilight_at(midnight + h * 3600 + m * 60);
+
+        assert_eq!(at(3, 20), Twilight::Civil);
+        assert_eq!(at(3, 40), Twilight::Civil);
+        assert_eq!(at(3, 46), Twilight::Day);
+        assert_eq!(at(12, 0), Twilight::Day);
+        assert_eq!(at(20, 18), Twilight::Day);
+        assert_eq!(at(20, 24), Twilight::Civil);
+
+        // the sun never gets 18 degrees below horizon around midsummer
+        let darkest = (0..24 * 60).map(|m| at(0, m)).max().unwrap();
+        assert_eq!(darkest, Twilight::Astronomical);
+
+        // noon elevation is 90 degrees minus latitude plus declination
+        let noon = sun_elevation_from_unix_timestamp(&GREENWICH, midnight + 12 * 3600);
+        assert!((noon - Decidegrees(620)).abs() <= Decidegrees(5));
+    }
+}
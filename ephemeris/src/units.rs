@@ -0,0 +1,138 @@
+use core::fmt;
+use core::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+/// Angle in hundredths of degree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Centidegrees(pub i32);
+
+/// Angle in tenths of degree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Decidegrees(pub i32);
+
+/// Angle in whole degrees
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Degrees(pub i32);
+
+macro_rules! angle {
+    ($unit:ident, $per_degree:expr, $decimals:expr) => {
+        impl $unit {
+            /// Number of units in one degree
+            pub const PER_DEGREE: i32 = $per_degree;
+
+            /// Angle in degrees
+            pub fn degrees(self) -> f64 {
+                self.0 as f64 / Self::PER_DEGREE as f64
+            }
+
+            /// Absolute value of the angle
+            pub fn abs(self) -> Self {
+                $unit(self.0.abs())
+            }
+        }
+
+        impl Add for $unit {
+            type Output = Self;
+            fn add(self, rhs: Self) -> Self {
+                $unit(self.0 + rhs.0)
+            }
+        }
+
+        impl Sub for $unit {
+            type Output = Self;
+            fn sub(self, rhs: Self) -> Self {
+                $unit(self.0 - rhs.0)
+            }
+        }
+
+        impl AddAssign for $unit {
+            fn add_assign(&mut self, rhs: Self) {
+                self.0 += rhs.0;
+            }
+        }
+
+        impl SubAssign for $unit {
+            fn sub_assign(&mut self, rhs: Self) {
+                self.0 -= rhs.0;
+            }
+        }
+
+        impl Neg for $unit {
+            type Output = Self;
+            fn neg(self) -> Self {
+                $unit(-self.0)
+            }
+        }
+
+        impl Mul<i32> for $unit {
+            type Output = Self;
+            fn mul(self, rhs: i32) -> Self {
+                $unit(self.0 * rhs)
+            }
+        }
+
+        impl Div<i32> for $unit {
+            type Output = Self;
+            fn div(self, rhs: i32) -> Self {
+                $unit(self.0 / rhs)
+            }
+        }
+
+        impl fmt::Display for $unit {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let sign = if self.0 < 0 { "-" } else { "" };
+                let v = self.0.unsigned_abs();
+                let p = Self::PER_DEGREE as u32;
+                match $decimals {
+                    0 => write!(f, "{sign}{v}°"),
+                    d => write!(f, "{sign}{}.{:0d$}°", v / p, v % p, d = d),
+                }
+            }
+        }
+    };
+}
+
+angle!(Centidegrees, 100, 2);
+angle!(Decidegrees, 10, 1);
+angle!(Degrees, 1, 0);
+
+impl From<Decidegrees> for Centidegrees {
+    fn from(a: Decidegrees) -> Self {
+        Centidegrees(10 * a.0)
+    }
+}
+
+impl From<Degrees> for Centidegrees {
+    fn from(a: Degrees) -> Self {
+        Centidegrees(100 * a.0)
+    }
+}
+
+impl From<Degrees> for Decidegrees {
+    fn from(a: Degrees) -> Self {
+        Decidegrees(10 * a.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions_and_display() {
+        assert_eq!(Centidegrees::from(Decidegrees(-45)), Centidegrees(-450));
+        assert_eq!(Centidegrees::from(Degrees(90)), Centidegrees(9000));
+        assert_eq!(Decidegrees::from(Degrees(-90)), Decidegrees(-900));
+        assert_eq!(Centidegrees(12345).degrees(), 123.45);
+
+        assert_eq!(
+            Centidegrees(100) + Decidegrees(5).into() - Centidegrees(1),
+            Centidegrees(149)
+        );
+        assert_eq!(-Decidegrees(3) * 2 / 3, Decidegrees(-2));
+
+        assert_eq!(format!("{}", Centidegrees(12345)), "123.45°");
+        assert_eq!(format!("{}", Centidegrees(-5)), "-0.05°");
+        assert_eq!(format!("{}", Decidegrees(-905)), "-90.5°");
+        assert_eq!(format!("{}", Degrees(7)), "7°");
+    }
+}
@@ -0,0 +1,166 @@
+use crate::defs::{Channel, Ephemeris, MoonEphemeris};
+use crate::rules::{check_elevation, check_shadow, validate_tables, Violation, ViolationKind};
+
+/// Iterate over every rule violation found in provided ephemeris
+///
+/// A zero period is reported alone, otherwise every sample of both channels
+/// is checked in timestamp order.
+pub fn validate<E: Ephemeris + ?Sized>(data: &E) -> Violations<'_, E> {
+    let shadow = data.shadow().len();
+    let elevation = data.elevation().len();
+    Violations {
+        data,
+        position: 0,
+        end: 2 * shadow.max(elevation),
+        pending: match (data.period(), shadow == elevation) {
+            (0, _) => Some(ViolationKind::InvalidPeriod),
+            (_, false) => Some(ViolationKind::LengthMismatch { shadow, elevation }),
+            _ => None,
+        },
+    }
+}
+
+/// Return the first rule violation found in provided ephemeris
+///
+/// Can be used to reject a table at compile time:
+/// `const _: () = assert!(first_violation(&TABLE).is_none());`
+pub const fn first_violation(data: &MoonEphemeris) -> Option<Violation> {
+    validate_tables(data.start, data.period, data.shadow, data.elevation)
+}
+
+/// Iterator returned by `validate`
+pub struct Violations<'a, E: ?Sized> {
+    data: &'a E,
+    // even positions check shadow samples, odd ones elevation samples
+    position: usize,
+    end: usize,
+    // table wide violation not reported yet
+    pending: Option<ViolationKind>,
+}
+
+impl<E: Ephemeris + ?Sized> Iterator for Violations<'_, E> {
+    type Item = Violation;
+
+    fn next(&mut self) -> Option<Violation> {
+        let (start, period) = (self.data.start(), self.data.period());
+
+        if let Some(kind) = self.pending.take() {
+            let index = match kind {
+                ViolationKind::LengthMismatch { shadow, elevation } => shadow.min(elevation),
+                _ => {
+                    // nothing else can be checked without a period
+                    self.position = self.end;
+                    0
+                }
+            };
+            return Some(Violation::at(start, period, index, kind));
+        }
+
+        while self.position < self.end {
+            let (index, is_shadow) = (self.position / 2, self.position % 2 == 0);
+            self.position += 1;
+
+            let kind = match is_shadow {
+                true => check_sample(self.data.shadow(), index, period, check_shadow),
+                false => check_sample(self.data.elevation(), index, period, check_elevation),
+            };
+            if let Some(kind) = kind {
+                return Some(Violation::at(start, period, index, kind));
+            }
+        }
+        None
+    }
+}
+
+/// Run provided rule on a channel sample, if the channel holds it
+fn check_sample<C: Channel + ?Sized>(
+    channel: &C,
+    index: usize,
+    period: u32,
+    rule: fn(Option<i32>, i32, u32) -> Option<ViolationKind>,
+) -> Option<ViolationKind> {
+    let value = channel.sample(index)?;
+    let prev = index.checked_sub(1).and_then(|i| channel.sample(i));
+    rule(prev, value, period)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MOON_EPHEMERIS;
+
+    // reject the bundled table at compile time
+    const _: () = assert!(first_violation(&MOON_EPHEMERIS).is_none());
+
+    #[test]
+    fn bundled_table_is_valid() {
+        assert_eq!(validate(&MOON_EPHEMERIS).next(), None);
+    }
+
+    #[test]
+    fn violations_are_located() {
+        const BROKEN: MoonEphemeris = MoonEphemeris {
+            start: 1000,
+            period: 3600,
+            shadow: &[10, 3595, 3590, 3700, 3000],
+            elevation: &[10, 20, 30, -91],
+        };
+
+        let violations: Vec<_> = validate(&BROKEN).collect();
+        assert_eq!(
+            violations,
+            [
+                Violation {
+                    index: 4,
+                    unix: 1000 + 4 * 3600,
+                    kind: ViolationKind::LengthMismatch {
+                        shadow: 5,
+                        elevation: 4
+                    }
+                },
+                Violation {
+                    index: 3,
+                    unix: 1000 + 3 * 3600,
+                    kind: ViolationKind::ShadowOutOfRange(3700)
+                },
+                Violation {
+                    index: 3,
+                    unix: 1000 + 3 * 3600,
+                    kind: ViolationKind::ElevationOutOfRange(-91)
+                },
+                Violation {
+                    index: 4,
+                    unix: 1000 + 4 * 3600,
+                    kind: ViolationKind::ShadowJump(-700)
+                },
+            ]
+        );
+        assert_eq!(first_violation(&BROKEN), Some(violations[0]));
+    }
+
+    #[test]
+    fn jumps_depend_on_period() {
+        let mut table = MoonEphemeris {
+            start: 0,
+            period: 3600,
+            shadow: &[1000, 870],
+            elevation: &[0, 18],
+        };
+        let kinds = |t: &MoonEphemeris| validate(t).map(|v| v.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds(&table),
+            [
+                ViolationKind::ShadowJump(-130),
+                ViolationKind::ElevationJump(18)
+            ]
+        );
+
+        // samples further apart may move more
+        table.period = 2 * 3600;
+        assert_eq!(kinds(&table), []);
+        assert_eq!(first_violation(&table), None);
+
+        table.period = 0;
+        assert_eq!(kinds(&table), [ViolationKind::InvalidPeriod]);
+    }
+}
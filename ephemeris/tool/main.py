@@ -1,7 +1,13 @@
+"""Draw moon shadow angles to eyeball them against the sky.
+
+The bundled ephemeris table is no longer produced here, the ephemeris crate
+generates it from ephemeris.conf, see src/generate.rs.
+"""
+
 from astropy.time import Time
 import time
-from astropy.coordinates import solar_system_ephemeris, EarthLocation, AltAz
-from astropy.coordinates import get_body, get_body_barycentric
+from astropy.coordinates import solar_system_ephemeris
+from astropy.coordinates import get_body_barycentric
 import numpy as np
 from PIL import Image, ImageDraw
 
@@ -47,27 +53,8 @@ def normalize_0_2pi(a):
 
 
 def main():
-    # genangles()
-    # gen_dates()
-    gen_ephemeris()
-
-
-def get_elevation(t) -> float:
-    """Return moon elevation at given time t in degrees"""
-    with solar_system_ephemeris.set("builtin"):
-
-        # get moon position
-        loc = EarthLocation.from_geodetic(-0.55, 44.85)
-        moon = get_body("moon", t, loc)
-
-        # convert moon position to an elevation / azimuth frame
-        altazframe = AltAz(obstime=t, location=loc, pressure=0)
-        moonaz = moon.transform_to(altazframe)
-
-        # elevation is in degrees
-        elevation = moonaz.alt.degree
-
-        return elevation
+    # gen_angles()
+    gen_dates()
 
 
 def get_shadow_angle(t) -> int:
@@ -105,52 +92,6 @@ def get_shadow_angle(t) -> int:
     return alpha
 
 
-def gen_ephemeris():
-
-    # epherids will start at curent unix timestamp
-    now = int(time.time())
-
-    # N = 10 * 365 * 24  # 10 years
-    N = 24  # 1 day
-    T = 3600
-
-    print("use crate::defs::MoonEphemeris;")
-    print("pub const MOON_EPHEMERIS: MoonEphemeris = MoonEphemeris {")
-    print(f"    start: {now},")
-    print(f"    period: {T},")
-
-    # compute moon angles each hour for 10 years
-    angles = []
-    for h in range(0, N):
-
-        t = Time(now + h * T, format="unix")
-        # -- shadow angle
-        alpha = get_shadow_angle(t)
-        # convert angle to decidegrees
-        alpha = int(10 * np.degrees(alpha))
-
-        # -- moon elevation
-        elevation = get_elevation(t)
-        # elevation will be stored as a i8 in degrees
-        # elevation is ranging from -90 to +90 so representation should fit
-        elevation = int(elevation)
-
-        angles.append((alpha, elevation))
-
-    # output values
-    print("    shadow: &[")
-    for a, _ in angles:
-        print(f"        {a},")
-    print("    ],")
-
-    print("    elevation: &[")
-    for _, e in angles:
-        print(f"        {e},")
-    print("    ],")
-
-    print("};")
-
-
 def gen_angles():
     # -- TEST draw all angles --
     W, H = (500, 1000)
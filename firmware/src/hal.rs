@@ -0,0 +1,122 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use esp_idf_svc::hal::gpio::{Input, Pin, PinDriver};
+use esp_idf_svc::hal::ledc::LedcDriver;
+use esp_idf_svc::nvs::{EspNvs, NvsDefault};
+use esp_idf_svc::sys::EspError;
+
+use moon_core::{Backlight, GlobeMotor, IndexSensor, Storage, WallClock};
+
+use crate::stepper::EspStepper;
+
+impl GlobeMotor for EspStepper {
+    type Error = EspError;
+
+    fn step(&mut self, steps: i32) -> Result<(), EspError> {
+        self.move_by(steps as i64);
+        self.wait();
+        Ok(())
+    }
+
+    fn start(&mut self, steps: i32) -> Result<(), EspError> {
+        self.move_by(steps as i64);
+        Ok(())
+    }
+
+    fn is_moving(&mut self) -> Result<bool, EspError> {
+        Ok(EspStepper::is_moving(self))
+    }
+
+    fn release(&mut self) -> Result<(), EspError> {
+        EspStepper::release(self);
+        Ok(())
+    }
+}
+
+/// Optical fork wired to an input pin, high while the index is seen
+pub struct EspIndexSensor<'d, P: Pin> {
+    pin: PinDriver<'d, P, Input>,
+}
+
+impl<'d, P: Pin> EspIndexSensor<'d, P> {
+    pub fn new(pin: PinDriver<'d, P, Input>) -> Self {
+        EspIndexSensor { pin }
+    }
+}
+
+impl<P: Pin> IndexSensor for EspIndexSensor<'_, P> {
+    type Error = EspError;
+
+    fn is_detected(&mut self) -> Result<bool, EspError> {
+        Ok(self.pin.is_high())
+    }
+}
+
+/// RGB backlight on three LEDC channels
+pub struct EspBacklight<'d> {
+    r: LedcDriver<'d>,
+    g: LedcDriver<'d>,
+    b: LedcDriver<'d>,
+}
+
+impl<'d> EspBacklight<'d> {
+    pub fn new(r: LedcDriver<'d>, g: LedcDriver<'d>, b: LedcDriver<'d>) -> Self {
+        EspBacklight { r, g, b }
+    }
+}
+
+impl Backlight for EspBacklight<'_> {
+    type Error = EspError;
+
+    fn set_color(&mut self, r: u8, g: u8, b: u8) -> Result<(), EspError> {
+        for (driver, duty) in [(&mut self.r, r), (&mut self.g, g), (&mut self.b, b)] {
+            // scale u8 duty cycle to the channel resolution
+            let max = driver.get_max_duty();
+            driver.set_duty(duty as u32 * max / 255)?;
+        }
+        Ok(())
+    }
+}
+
+/// System clock, set by SNTP once synchronized
+pub struct SystemClock;
+
+impl SystemClock {
+    /// System time starts at unix epoch on boot, anything before this date
+    /// means SNTP did not sync yet
+    const SYNCED_AFTER: i64 = 1_735_689_600; // 2025-01-01
+}
+
+impl WallClock for SystemClock {
+    fn now(&self) -> Option<i64> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+        (now >= Self::SYNCED_AFTER).then_some(now)
+    }
+}
+
+/// Storage in a NVS namespace
+pub struct NvsStorage {
+    nvs: EspNvs<NvsDefault>,
+}
+
+impl NvsStorage {
+    pub fn new(nvs: EspNvs<NvsDefault>) -> Self {
+        NvsStorage { nvs }
+    }
+}
+
+impl Storage for NvsStorage {
+    type Error = EspError;
+
+    fn get(&mut self, key: &str, buf: &mut [u8]) -> Result<Option<usize>, EspError> {
+        Ok(self.nvs.get_raw(key, buf)?.map(|v| v.len()))
+    }
+
+    fn set(&mut self, key: &str, value: &[u8]) -> Result<(), EspError> {
+        self.nvs.set_raw(key, value).map(|_| ())
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), EspError> {
+        self.nvs.remove(key).map(|_| ())
+    }
+}
@@ -1,5 +1,4 @@
 use esp_idf_svc::eventloop::EspSystemEventLoop;
-use esp_idf_svc::hal::delay::*;
 use esp_idf_svc::hal::gpio::*;
 use esp_idf_svc::hal::ledc::config::TimerConfig;
 use esp_idf_svc::hal::ledc::*;
@@ -14,8 +13,15 @@ use std::time::Duration;
 use log::*;
 
 mod every;
+mod hal;
+mod stepper;
 
-use ephemeris::{shadow_angle_from_unix_timestamp, MOON_EPHEMERIS};
+use ephemeris::{Ephemeris, EphemerisError, MOON_EPHEMERIS};
+use moon_core::{Backlight, Devices, Error, Moon, Status, StepperConfig, WallClock};
+
+use every::CallEvery;
+use hal::{EspBacklight, EspIndexSensor, NvsStorage, SystemClock};
+use stepper::EspStepper;
 
 fn main() -> Result<(), EspError> {
     // It is necessary to call this function once. Otherwise some patches to the runtime
@@ -31,7 +37,7 @@ fn main() -> Result<(), EspError> {
     let sysloop = EspSystemEventLoop::take()?;
 
     // -- NVS --
-    //let nvs = EspNvs::new(EspNvsPartition::<NvsDefault>::take().unwrap(), "moon", true).unwrap();
+    let nvs = EspNvs::new(EspNvsPartition::<NvsDefault>::take()?, "moon", true)?;
 
     //// -- WIFI --
     //let ssid = "Livebox-DE90";
@@ -70,112 +76,99 @@ fn main() -> Result<(), EspError> {
     //    std::thread::sleep(Duration::from_secs(1));
     //}
 
-    //// -- MOON BACKLIGHT --
-    //let timer_driver = LedcTimerDriver::new(
-    //    p.ledc.timer0,
-    //    &TimerConfig::default()
-    //        .frequency(1000.Hz().into())
-    //        .resolution(Resolution::Bits14),
-    //)
-    //.unwrap();
-
-    //let mut dr = LedcDriver::new(p.ledc.channel0, &timer_driver, p.pins.gpio7).unwrap();
-    //let mut dg = LedcDriver::new(p.ledc.channel1, &timer_driver, p.pins.gpio8).unwrap();
-    //let mut db = LedcDriver::new(p.ledc.channel2, &timer_driver, p.pins.gpio9).unwrap();
-
-    //// build a helper function to set backlight
-    //let mut set_backlight = move |r: u8, g: u8, b: u8| {
-    //    let duty = |duty: u8, max: u32| -> u32 {
-    //        // scale u8 duty cycle to proper one
-    //        (duty as u32) * 255 / max
-    //    };
-
-    //    dr.set_duty(duty(r, dr.get_max_duty())).ok();
-    //    dg.set_duty(duty(g, dg.get_max_duty())).ok();
-    //    db.set_duty(duty(b, db.get_max_duty())).ok();
-    //};
-
-    //// quick backlight check
-    //for (r, g, b) in [(255, 0, 0), (0, 255, 0), (0, 0, 255)] {
-    //    set_backlight(r, g, b);
-    //    std::thread::sleep(Duration::from_millis(100));
-    //}
-    //// turn backlight off
-    //set_backlight(0, 0, 0);
-
-    //// -- GLOBE INDEX --
-    //// turn optical fork on
-    //let mut index_led = PinDriver::output(p.pins.gpio5)?;
-    //index_led.set_high()?;
-    //// setup index as input pulled low
-    //let mut index = PinDriver::input(p.pins.gpio6)?;
-    //index.set_pull(Pull::Down)?;
-
-    //// build a helper function to accomodate sensor polarity
-    //let index_detected = move || {
-    //    //
-    //    match index.is_high() {
-    //        true => true,
-    //        false => false,
-    //    }
-    //};
+    // -- MOON BACKLIGHT --
+    let timer_driver = LedcTimerDriver::new(
+        p.ledc.timer0,
+        &TimerConfig::default()
+            .frequency(1000.Hz().into())
+            .resolution(Resolution::Bits14),
+    )?;
+
+    let mut backlight = EspBacklight::new(
+        LedcDriver::new(p.ledc.channel0, &timer_driver, p.pins.gpio7)?,
+        LedcDriver::new(p.ledc.channel1, &timer_driver, p.pins.gpio8)?,
+        LedcDriver::new(p.ledc.channel2, &timer_driver, p.pins.gpio9)?,
+    );
+
+    // quick backlight check
+    for (r, g, b) in [(255, 0, 0), (0, 255, 0), (0, 0, 255)] {
+        backlight.set_color(r, g, b)?;
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    // turn backlight off
+    backlight.set_color(0, 0, 0)?;
+
+    // -- GLOBE INDEX --
+    // optical fork LED, on gpio4 since gpio5 drives a motor coil, kept lit
+    // for as long as the driver lives
+    let mut index_led = PinDriver::output(p.pins.gpio4)?;
+    index_led.set_high()?;
+    // setup index as input pulled low
+    let mut index = PinDriver::input(p.pins.gpio6)?;
+    index.set_pull(Pull::Down)?;
 
     // -- STEPPER MOTOR --
-    let m1 = PinDriver::output(p.pins.gpio5)?;
-    let m2 = PinDriver::output(p.pins.gpio18)?;
-    let m3 = PinDriver::output(p.pins.gpio19)?;
-    let m4 = PinDriver::output(p.pins.gpio21)?;
-
-    // configure and energize motor
-    const STEPS_PER_REV: u32 = 4096;
-    let mut motor = embedded_stepper::create_stepper_4pin(m1, m3, m2, m4, Ets, STEPS_PER_REV);
-    // set speed in RPMs
-    motor.set_speed(5);
+    // 28BYJ-48 through its ULN2003 board, coils in driver input order
+    let coils = [
+        PinDriver::output(p.pins.gpio5.downgrade_output())?,
+        PinDriver::output(p.pins.gpio18.downgrade_output())?,
+        PinDriver::output(p.pins.gpio19.downgrade_output())?,
+        PinDriver::output(p.pins.gpio21.downgrade_output())?,
+    ];
+    // half steps ramping up and down, driven from its own task
+    let motor = EspStepper::spawn(coils, StepperConfig::DEFAULT);
+
+    let mut moon = Moon::new(
+        &MOON_EPHEMERIS,
+        Devices {
+            motor,
+            index: EspIndexSensor::new(index),
+            backlight,
+            clock: SystemClock,
+            storage: NvsStorage::new(nvs),
+        },
+    );
+
+    // trust globe position saved before shutdown, index confirms it later
+    match moon.resume() {
+        Ok(true) => info!("globe position restored at {:?}", moon.position()),
+        Ok(false) => info!("globe position unknown, homing on first update"),
+        Err(e) => error!("{e}"),
+    }
 
+    // -- MAIN LOOP --
+    let mut update = CallEvery::<60_000>::new();
     loop {
-        info!("TICK");
-        for _ in 0..5 {
-            motor.step(100);
-            std::thread::sleep(Duration::from_millis(100));
-        }
-        for _ in 0..5 {
-            motor.step(-100);
-            std::thread::sleep(Duration::from_millis(100));
-        }
+        update.call(|| {
+            let Some(now) = moon.devices().clock.now() else {
+                warn!("clock not synced yet");
+                return;
+            };
+            info!("DATE: {now}");
+
+            // warn well before bundled table expires
+            const EXPIRY_WARNING_S: u64 = 90 * 24 * 3600;
+            let remaining = MOON_EPHEMERIS.remaining(now);
+            if remaining < EXPIRY_WARNING_S {
+                warn!("ephemeris expires in {} days", remaining / (24 * 3600));
+            }
+
+            match moon.tick() {
+                Ok(Status::Tracking(angle)) => info!("ANGLE = {angle}"),
+                Ok(Status::Expired(angle)) => {
+                    // table expired, globe follows the analytic lunar model
+                    warn!("ephemeris expired");
+                    info!("ANGLE = {angle}")
+                }
+                Ok(Status::Unsynced) => {}
+                Err(Error::Ephemeris(EphemerisError::BeforeStart)) => {
+                    // synced clock earlier than the table, likely a bad NTP answer
+                    error!("clock is before ephemeris start, globe left untouched")
+                }
+                Err(e) => error!("{e}"),
+            }
+        });
+
+        std::thread::sleep(Duration::from_millis(10));
     }
-
-    //// indexing steps
-    //const INDEXING_STEPS_FAST: i32 = 10;
-    //const INDEXING_STEPS_SLOW: i32 = 1;
-    //// index motor by turning positive until INDEX is not seen by sensor
-    //while index_detected() {
-    //    motor.step(INDEXING_STEPS_FAST).ok();
-    //}
-    //// continue indexing by turning positive until INDEX is seen
-    //while !index_detected() {
-    //    motor.step(INDEXING_STEPS_FAST).ok();
-    //}
-    //// finalize indexing by turning positive slowly until INDEX is not seen
-    //while index_detected() {
-    //    motor.step(INDEXING_STEPS_SLOW).ok();
-    //    std::thread::sleep(Duration::from_millis(1));
-    //}
-    //// at this point motor should be at located at mechanical reference
-    //const ZERO_INDEX_OFFSET: u32 = 0;
-    //// winding motor position in steps
-    //let mut motor_position = ZERO_INDEX_OFFSET;
-
-    //// -- MAIN LOOP --
-    //loop {
-    //    // get current unix timestamp
-    //    let now = chrono::Utc::now();
-    //    info!("DATE: {now:?} {}", now.timestamp());
-
-    //    // compute moon shadow angle from unix timestamp
-    //    let angle =
-    //        ephemeris::shadow_angle_from_unix_timestamp(&MOON_EPHEMERIS, now.timestamp()).unwrap();
-    //    info!("ANGLE = {angle}");
-
-    //    std::thread::sleep(Duration::from_millis(10));
-    //}
 }
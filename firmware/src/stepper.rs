@@ -0,0 +1,135 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use esp_idf_svc::hal::delay::Ets;
+use esp_idf_svc::hal::gpio::{AnyOutputPin, Output, PinDriver};
+use esp_idf_svc::sys::EspError;
+
+use log::*;
+
+use moon_core::{Coils, Stepper, StepperConfig};
+
+/// Coil pins in driver input order
+pub type CoilPins = [PinDriver<'static, AnyOutputPin, Output>; 4];
+
+/// Waits shorter than a FreeRTOS tick are busy waited
+const TICK_US: u64 = 1000;
+
+struct Shared {
+    stepper: Mutex<Stepper>,
+    // signaled on new targets and once the motor is idle
+    changed: Condvar,
+}
+
+/// Stepper motor driven from its own task
+///
+/// Moves run in the background, callers only set targets and may wait for
+/// the motor to come to rest.
+pub struct EspStepper {
+    shared: Arc<Shared>,
+}
+
+impl EspStepper {
+    /// Start driving provided coil pins
+    pub fn spawn(pins: CoilPins, config: StepperConfig) -> Self {
+        let shared = Arc::new(Shared {
+            stepper: Mutex::new(Stepper::new(config)),
+            changed: Condvar::new(),
+        });
+
+        let task = shared.clone();
+        std::thread::Builder::new()
+            .name("stepper".into())
+            .stack_size(4096)
+            .spawn(move || {
+                if let Err(e) = run(&task, pins) {
+                    error!("stepper task stopped: {e}");
+                }
+            })
+            .expect("failed to spawn stepper task");
+
+        EspStepper { shared }
+    }
+
+    fn stepper(&self) -> std::sync::MutexGuard<'_, Stepper> {
+        self.shared.stepper.lock().unwrap()
+    }
+
+    /// Head for provided position without waiting
+    pub fn move_to(&self, target: i64) {
+        self.stepper().move_to(target);
+        self.shared.changed.notify_all();
+    }
+
+    /// Head for a position relative to the current target without waiting
+    pub fn move_by(&self, steps: i64) {
+        self.stepper().move_by(steps);
+        self.shared.changed.notify_all();
+    }
+
+    pub fn position(&self) -> i64 {
+        self.stepper().position()
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.stepper().is_moving()
+    }
+
+    /// Block until the motor is at rest on its target
+    pub fn wait(&self) {
+        let stepper = self.stepper();
+        let _ = self
+            .shared
+            .changed
+            .wait_while(stepper, |s| s.is_moving())
+            .unwrap();
+    }
+
+    /// De-energize coils once at rest
+    pub fn release(&self) {
+        self.stepper().release();
+        self.shared.changed.notify_all();
+    }
+}
+
+/// Stepper task body, steps on schedule and sleeps while idle
+fn run(shared: &Shared, mut pins: CoilPins) -> Result<(), EspError> {
+    let start = Instant::now();
+    let now = || start.elapsed().as_micros() as u64;
+
+    let mut written: Coils = [false; 4];
+    for pin in pins.iter_mut() {
+        pin.set_low()?;
+    }
+
+    let mut stepper = shared.stepper.lock().unwrap();
+    loop {
+        stepper.poll(now());
+        let coils = stepper.coils();
+        if coils != written {
+            for (pin, on) in pins.iter_mut().zip(coils) {
+                pin.set_level(on.into())?;
+            }
+            written = coils;
+        }
+
+        match stepper.next_step_at() {
+            Some(at) => {
+                let wait = at.saturating_sub(now());
+                if wait > TICK_US {
+                    // sleep most of the way, a new target wakes us early
+                    let timeout = Duration::from_micros(wait - TICK_US);
+                    stepper = shared.changed.wait_timeout(stepper, timeout).unwrap().0;
+                } else {
+                    drop(stepper);
+                    Ets::delay_us(wait as u32);
+                    stepper = shared.stepper.lock().unwrap();
+                }
+            }
+            None => {
+                shared.changed.notify_all();
+                stepper = shared.changed.wait(stepper).unwrap();
+            }
+        }
+    }
+}